@@ -27,6 +27,58 @@ pub struct BotData {
     #[serde(default)]
     pub load_custom_chips: bool,
     pub bot_id: u64,
+    /// Where library changes are posted after a reload, 0 to not post them
+    #[serde(default)]
+    pub changelog_channel: u64,
+    #[serde(default)]
+    pub folder_rules: FolderRules,
+    #[serde(default)]
+    pub navicust_rules: NaviCustRules,
+}
+
+/// The limits every saved folder must follow
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct FolderRules {
+    pub max_size: usize,
+    pub max_copies: usize,
+    pub max_mega: usize,
+    pub max_giga: usize,
+    pub max_dark: usize,
+}
+
+impl Default for FolderRules {
+    fn default() -> Self {
+        FolderRules {
+            max_size: 30,
+            max_copies: 3,
+            max_mega: 5,
+            max_giga: 1,
+            max_dark: 3,
+        }
+    }
+}
+
+/// The limits every saved Navi-Cust loadout must follow
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct NaviCustRules {
+    /// The EB a loadout may spend when its owner has not set their own budget
+    pub default_budget: u16,
+    /// The most colours a loadout may use, not counting Gray
+    pub max_colours: usize,
+    /// The most Gray bug parts a loadout may hold
+    pub max_bug_parts: usize,
+}
+
+impl Default for NaviCustRules {
+    fn default() -> Self {
+        NaviCustRules {
+            default_budget: 10,
+            max_colours: 4,
+            max_bug_parts: 1,
+        }
+    }
 }
 
 impl BotData {
@@ -0,0 +1,196 @@
+use rand::Rng;
+
+use std::fmt::Write;
+
+use super::{DiceError, MAX_DICE};
+
+/// Dice which roll this value or lower are re-rolled once when re-rolling, keeping the higher result
+pub(crate) const REROLL_THRESHOLD: i16 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl std::fmt::Display for BinOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinOp::Add => write!(f, "+"),
+            BinOp::Sub => write!(f, "-"),
+            BinOp::Mul => write!(f, "*"),
+            BinOp::Div => write!(f, "/"),
+        }
+    }
+}
+
+/// Which dice of a roll count towards its total
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Keep {
+    Highest(u16),
+    Lowest(u16),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct DiceTerm {
+    pub count: u16,
+    pub sides: i16,
+    pub keep: Option<Keep>,
+    pub explode: bool,
+}
+
+pub(crate) enum Expr {
+    Number(i64),
+    Dice(DiceTerm),
+    Neg(Box<Expr>),
+    Group(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+/// Everything produced while rolling an expression
+pub(crate) struct RollState<'r, R: Rng> {
+    rng: &'r mut R,
+    reroll: bool,
+    pub rolls: Vec<i16>,
+    pub breakdown: String,
+}
+
+impl<'r, R: Rng> RollState<'r, R> {
+    pub fn new(rng: &'r mut R, reroll: bool) -> RollState<'r, R> {
+        RollState {
+            rng,
+            reroll,
+            rolls: vec![],
+            breakdown: String::new(),
+        }
+    }
+
+    pub(super) fn roll_one(&mut self, sides: i16) -> Result<i16, DiceError> {
+        let mut val = self.rng.gen_range(1..=sides);
+        if self.reroll && val <= REROLL_THRESHOLD {
+            let new_val = self.rng.gen_range(1..=sides);
+            if new_val > val {
+                val = new_val;
+            }
+        }
+        self.rolls.push(val);
+
+        if self.rolls.len() >= MAX_DICE {
+            return Err(DiceError::TooManyDice);
+        }
+
+        Ok(val)
+    }
+}
+
+impl DiceTerm {
+    /// Rolls each die of the term, returning the total of each die (including explosions)
+    /// along with whether or not it exploded
+    pub fn roll_each<R: Rng>(&self, state: &mut RollState<R>) -> Result<Vec<(i64, bool)>, DiceError> {
+        let mut dice = Vec::with_capacity(self.count as usize);
+        for _ in 0..self.count {
+            let mut val = state.roll_one(self.sides)?;
+            let mut total = val as i64;
+            let mut exploded = false;
+            while self.explode && val == self.sides {
+                exploded = true;
+                val = state.roll_one(self.sides)?;
+                total += val as i64;
+            }
+            dice.push((total, exploded));
+        }
+        Ok(dice)
+    }
+
+    /// Returns which indices of the rolled dice are kept
+    pub fn kept(&self, dice: &[(i64, bool)]) -> Vec<bool> {
+        let (amount, highest) = match self.keep {
+            Some(Keep::Highest(amount)) => (amount as usize, true),
+            Some(Keep::Lowest(amount)) => (amount as usize, false),
+            None => return vec![true; dice.len()],
+        };
+
+        let mut order = (0..dice.len()).collect::<Vec<usize>>();
+        if highest {
+            order.sort_by(|a, b| dice[*b].0.cmp(&dice[*a].0));
+        } else {
+            order.sort_by(|a, b| dice[*a].0.cmp(&dice[*b].0));
+        }
+
+        let mut kept = vec![false; dice.len()];
+        for index in order.into_iter().take(amount) {
+            kept[index] = true;
+        }
+        kept
+    }
+}
+
+impl Expr {
+    pub fn roll<R: Rng>(&self, state: &mut RollState<R>) -> Result<i64, DiceError> {
+        match self {
+            Expr::Number(num) => {
+                let _ = write!(state.breakdown, "{}", num);
+                Ok(*num)
+            }
+            Expr::Dice(term) => {
+                let dice = term.roll_each(state)?;
+                let kept = term.kept(&dice);
+
+                state.breakdown.push('[');
+                let mut total: i64 = 0;
+                for (index, ((val, exploded), keep)) in dice.iter().zip(kept.iter()).enumerate() {
+                    if index > 0 {
+                        state.breakdown.push_str(", ");
+                    }
+                    let marker = if *exploded { "!" } else { "" };
+                    if *keep {
+                        total += val;
+                        let _ = write!(state.breakdown, "{}{}", val, marker);
+                    } else {
+                        let _ = write!(state.breakdown, "~~{}{}~~", val, marker);
+                    }
+                }
+                state.breakdown.push(']');
+
+                Ok(total)
+            }
+            Expr::Neg(inner) => {
+                state.breakdown.push('-');
+                let val = inner.roll(state)?;
+                val.checked_neg().ok_or(DiceError::Overflow)
+            }
+            Expr::Group(inner) => {
+                state.breakdown.push('(');
+                let val = inner.roll(state)?;
+                state.breakdown.push(')');
+                Ok(val)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs_val = lhs.roll(state)?;
+                let _ = write!(state.breakdown, " {} ", op);
+                let rhs_val = rhs.roll(state)?;
+                let res = match op {
+                    BinOp::Add => lhs_val.checked_add(rhs_val),
+                    BinOp::Sub => lhs_val.checked_sub(rhs_val),
+                    BinOp::Mul => lhs_val.checked_mul(rhs_val),
+                    BinOp::Div => {
+                        if rhs_val == 0 {
+                            return Err(DiceError::DivideByZero);
+                        }
+                        // round down like most tabletop games do
+                        lhs_val.checked_div(rhs_val).map(|quot| {
+                            if lhs_val % rhs_val != 0 && (lhs_val < 0) != (rhs_val < 0) {
+                                quot - 1
+                            } else {
+                                quot
+                            }
+                        })
+                    }
+                };
+                res.ok_or(DiceError::Overflow)
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use serenity::{
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::{
+        channel::Message,
+        id::{ChannelId, GuildId, RoleId, UserId},
+        user::User,
+    },
+    prelude::*,
+};
+
+use std::collections::HashMap;
+
+use crate::util::dm_user;
+
+const GM_FILE: &str = "./gm_config.json";
+
+/// The most members of a GM role that will be sent a hidden roll
+const MAX_GM_DMS: usize = 10;
+
+/// Who is sent the result of hidden rolls in a channel
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(tag = "type", content = "id")]
+pub enum GameMaster {
+    User(u64),
+    Role(u64),
+}
+
+impl GameMaster {
+    /// Describes the GM by name, so that showing it doesn't ping anyone
+    async fn describe(&self, ctx: &Context) -> String {
+        match self {
+            GameMaster::User(id) => match UserId(*id).to_user(ctx).await {
+                Ok(user) => format!("the user {}", user.tag()),
+                Err(_) => format!("an unknown user ({})", id),
+            },
+            GameMaster::Role(id) => match RoleId(*id).to_role_cached(&ctx.cache).await {
+                Some(role) => format!("the role {}", role.name),
+                None => format!("an unknown role ({})", id),
+            },
+        }
+    }
+
+    /// Everyone who should be DMed a hidden roll, besides whoever rolled it
+    async fn recipients(&self, ctx: &Context, guild: Option<GuildId>, roller: UserId) -> Vec<UserId> {
+        match self {
+            GameMaster::User(id) if *id != roller.0 => vec![UserId(*id)],
+            GameMaster::User(_) => vec![],
+            GameMaster::Role(id) => {
+                let guild = match guild {
+                    Some(guild) => guild,
+                    None => return vec![],
+                };
+
+                // only the cached members are checked, asking discord for every member of a guild
+                // on each hidden roll is far too slow to answer a slash command in time
+                let role = RoleId(*id);
+                ctx.cache
+                    .guild_field(guild, |guild| {
+                        guild
+                            .members
+                            .values()
+                            .filter(|m| !m.user.bot && m.user.id != roller && m.roles.contains(&role))
+                            .map(|m| m.user.id)
+                            .take(MAX_GM_DMS)
+                            .collect()
+                    })
+                    .await
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// The GM of each channel which has one
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct GmConfig(HashMap<u64, GameMaster>);
+
+impl GmConfig {
+    /// Loads the saved GMs, starting fresh if there are none yet
+    pub async fn import() -> RwLock<GmConfig> {
+        let config = match tokio::fs::read_to_string(GM_FILE).await {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|why| {
+                eprintln!("Could not parse {}, starting with no GMs: {}", GM_FILE, why);
+                GmConfig::default()
+            }),
+            Err(_) => GmConfig::default(),
+        };
+        RwLock::new(config)
+    }
+
+    async fn save(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string(&self.0)?;
+        tokio::fs::write(GM_FILE, json).await?;
+        Ok(())
+    }
+
+    pub fn get(&self, channel: ChannelId) -> Option<GameMaster> {
+        self.0.get(&channel.0).copied()
+    }
+}
+
+impl TypeMapKey for GmConfig {
+    type Value = RwLock<GmConfig>;
+}
+
+/// DMs the result of a hidden roll to whoever rolled it and the channel's GM,
+/// returning the note to post publicly in its place
+pub(crate) async fn deliver_secret_roll(
+    ctx: &Context,
+    roller: &User,
+    guild: Option<GuildId>,
+    channel: ChannelId,
+    expr: &str,
+    result: &str,
+) -> String {
+    let gm = {
+        let data = ctx.data.read().await;
+        let gm_lock = data.get::<GmConfig>().expect("gm config not found");
+        let gm = gm_lock.read().await.get(channel);
+        gm
+    };
+
+    let to_roller = format!("You secretly rolled `{}` in <#{}>\n{}", expr.trim(), channel.0, result);
+    let mut note = format!("{} rolled secretly", roller.name);
+    if let Err(why) = dm_user(ctx, roller.id, to_roller).await {
+        eprintln!("Could not DM a secret roll to its roller: {:?}", why);
+        note.push_str(", but I could not DM them the result");
+    }
+
+    let recipients = match gm {
+        Some(gm) => gm.recipients(ctx, guild, roller.id).await,
+        None => vec![],
+    };
+
+    let to_gm = format!(
+        "{} secretly rolled `{}` in <#{}>\n{}",
+        roller.name,
+        expr.trim(),
+        channel.0,
+        result
+    );
+    for recipient in recipients {
+        if let Err(why) = dm_user(ctx, recipient, &to_gm).await {
+            eprintln!("Could not DM a secret roll to a GM: {:?}", why);
+        }
+    }
+
+    note
+}
+
+#[group]
+#[prefixes("gm")]
+#[only_in(guilds)]
+#[default_command(show_gm)]
+#[commands(show_gm, set_gm, clear_gm)]
+/// A group of commands for choosing who is sent hidden rolls made in a channel
+struct Gm;
+
+#[command("show")]
+/// Show who is sent the hidden rolls made in this channel
+async fn show_gm(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let gm_lock = data.get::<GmConfig>().expect("gm config not found");
+    let gm = gm_lock.read().await.get(msg.channel_id);
+
+    match gm {
+        Some(gm) => {
+            let gm = gm.describe(ctx).await;
+            reply!(ctx, msg, format!("The GM for this channel is {}", gm));
+        }
+        None => reply!(ctx, msg, "This channel has no GM, hidden rolls are only sent to whoever rolled them"),
+    }
+    Ok(())
+}
+
+#[command("set")]
+#[required_permissions("MANAGE_CHANNELS")]
+/// Set the user or role which is sent the hidden rolls made in this channel
+/// A role only reaches its members who have been seen by the bot
+#[example = "@Major"]
+#[example = "@GM"]
+async fn set_gm(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let arg = match args.single::<String>() {
+        Ok(arg) => arg,
+        Err(_) => {
+            reply!(ctx, msg, "You must mention the user or role to make this channel's GM");
+            return Ok(());
+        }
+    };
+
+    let gm = if let Ok(role) = arg.parse::<RoleId>() {
+        GameMaster::Role(role.0)
+    } else if let Ok(user) = arg.parse::<UserId>() {
+        GameMaster::User(user.0)
+    } else {
+        reply!(ctx, msg, format!("{} is not a user or a role", arg));
+        return Ok(());
+    };
+
+    let data = ctx.data.read().await;
+    let gm_lock = data.get::<GmConfig>().expect("gm config not found");
+    let mut config = gm_lock.write().await;
+    config.0.insert(msg.channel_id.0, gm);
+
+    if let Err(why) = config.save().await {
+        eprintln!("Could not save GMs: {:?}", why);
+        reply!(ctx, msg, "GM set, but it could not be saved and will be lost on a restart");
+        return Ok(());
+    }
+
+    let gm = gm.describe(ctx).await;
+    reply!(ctx, msg, format!("The GM for this channel is now {}", gm));
+    Ok(())
+}
+
+#[command("clear")]
+#[required_permissions("MANAGE_CHANNELS")]
+/// Stop sending this channel's hidden rolls to anyone but whoever rolled them
+async fn clear_gm(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let gm_lock = data.get::<GmConfig>().expect("gm config not found");
+    let mut config = gm_lock.write().await;
+
+    if config.0.remove(&msg.channel_id.0).is_none() {
+        reply!(ctx, msg, "This channel has no GM");
+        return Ok(());
+    }
+
+    if let Err(why) = config.save().await {
+        eprintln!("Could not save GMs: {:?}", why);
+    }
+
+    reply!(ctx, msg, "This channel no longer has a GM");
+    Ok(())
+}
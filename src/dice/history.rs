@@ -0,0 +1,490 @@
+use rand::{
+    rngs::{StdRng, ThreadRng},
+    Rng, SeedableRng,
+};
+use serde::Serialize;
+use serenity::{
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::{
+        channel::Message,
+        id::{ChannelId, UserId},
+        user::User,
+    },
+    prelude::*,
+};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use chrono::{TimeZone, Utc};
+use itertools::Itertools;
+use tokio::{io::AsyncWriteExt, task::JoinError};
+
+use super::{stats::StatMethod, DiceError, DieRoll};
+use crate::bot_data::BotData;
+
+/// Every recorded roll is also appended here, one JSON object per line
+const HISTORY_FILE: &str = "./roll_history.log";
+
+/// The most rolls kept in memory for each channel
+const MAX_CHANNEL_HISTORY: usize = 200;
+
+const DEFAULT_HISTORY_LEN: usize = 10;
+
+const MAX_HISTORY_LEN: usize = 50;
+
+/// The most rolls a seeded session will keep for verification
+const MAX_SESSION_ROLLS: usize = 1000;
+
+/// The most dice shown for a single roll in the history
+const MAX_SHOWN_DICE: usize = 20;
+
+/// The commands which can make a recorded roll, each knows how to repeat itself
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) enum RollKind {
+    Roll,
+    Reroll,
+    Pool,
+    Repool,
+    Stats,
+}
+
+struct Rolled {
+    reply: String,
+    result: String,
+    dice: Vec<i16>,
+}
+
+impl RollKind {
+    fn roll<R: Rng>(self, expr: &str, rng: &mut R) -> Result<Rolled, DiceError> {
+        match self {
+            RollKind::Roll | RollKind::Reroll => {
+                let res = DieRoll::roll_dice_with(expr, self == RollKind::Reroll, rng)?;
+                Ok(Rolled {
+                    reply: res.to_string(),
+                    result: res.total.to_string(),
+                    dice: res.rolls,
+                })
+            }
+            RollKind::Pool | RollKind::Repool => {
+                let res = DieRoll::roll_pool_with(expr, self == RollKind::Repool, rng)?;
+                Ok(Rolled {
+                    reply: res.to_string(),
+                    result: format!("{} successes", res.successes),
+                    dice: res.rolls,
+                })
+            }
+            RollKind::Stats => {
+                let method = expr.parse::<StatMethod>().unwrap_or(StatMethod::DropLowest);
+                let block = method.roll(rng)?;
+                Ok(Rolled {
+                    reply: block.to_string(),
+                    result: block.summary(),
+                    dice: block.dice,
+                })
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for RollKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RollKind::Roll => write!(f, "rolled"),
+            RollKind::Reroll => write!(f, "rerolled"),
+            RollKind::Pool => write!(f, "rolled a pool of"),
+            RollKind::Repool => write!(f, "rerolled a pool of"),
+            RollKind::Stats => write!(f, "rolled stats with"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct RollRecord {
+    user: u64,
+    user_name: String,
+    channel: u64,
+    kind: RollKind,
+    expression: String,
+    result: String,
+    dice: Vec<i16>,
+    timestamp: i64,
+    seeded: bool,
+    hidden: bool,
+}
+
+impl std::fmt::Display for RollRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let time = Utc.timestamp_opt(self.timestamp, 0).single().unwrap_or_else(Utc::now);
+        write!(
+            f,
+            "`{}` **{}** {} `{}`",
+            time.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.user_name,
+            self.kind,
+            self.expression
+        )?;
+
+        // only the roller and GM get to see the result of a hidden roll
+        if self.hidden {
+            write!(f, " secretly")?;
+        } else {
+            write!(f, ": {}", self.result)?;
+            // rolls like a BnB allocation draw no dice of their own
+            if !self.dice.is_empty() {
+                write!(f, " [{}", self.dice.iter().take(MAX_SHOWN_DICE).format(", "))?;
+                if self.dice.len() > MAX_SHOWN_DICE {
+                    write!(f, ", ...")?;
+                }
+                write!(f, "]")?;
+            }
+        }
+
+        if self.seeded {
+            write!(f, " (seeded)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The generator of a seeded session along with every roll it has made
+struct SessionRolls {
+    rng: StdRng,
+    /// Every attempted roll in order, along with whether it failed, as failures still use the generator
+    rolls: Vec<(RollRecord, bool)>,
+}
+
+/// A channel's rolls made with a seeded generator, so they can be replayed once the seed is known
+struct SeededSession {
+    seed: u64,
+    started_by: u64,
+    finished: bool,
+    /// Locked for the whole of each roll, so rolls are recorded in the order they use the generator
+    rolls: Arc<Mutex<SessionRolls>>,
+}
+
+/// Recent rolls for each channel, along with any seeded sessions
+#[derive(Default)]
+pub struct RollHistory {
+    channels: HashMap<u64, VecDeque<RollRecord>>,
+    sessions: HashMap<u64, SeededSession>,
+}
+
+impl RollHistory {
+    fn record(&mut self, record: RollRecord) {
+        let history = self.channels.entry(record.channel).or_default();
+        if history.len() >= MAX_CHANNEL_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(record);
+    }
+
+    fn recent(&self, channel: ChannelId, user: Option<UserId>, count: usize) -> Vec<String> {
+        let history = match self.channels.get(&channel.0) {
+            Some(history) => history,
+            None => return vec![],
+        };
+
+        let mut recent = history
+            .iter()
+            .rev()
+            .filter(|record| user.is_none_or(|user| record.user == user.0))
+            .take(count)
+            .map(|record| record.to_string())
+            .collect::<Vec<String>>();
+        recent.reverse();
+        recent
+    }
+}
+
+impl TypeMapKey for RollHistory {
+    type Value = RwLock<RollHistory>;
+}
+
+async fn append_to_file(record: &RollRecord) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(HISTORY_FILE)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Rolls for a user in a channel, recording the result in the channel's history
+/// If the channel has a seeded session the roll uses its generator instead of a random one
+pub(crate) async fn roll_and_record(
+    ctx: &Context,
+    user: &User,
+    channel: ChannelId,
+    kind: RollKind,
+    expr: String,
+    hidden: bool,
+) -> Result<Result<String, DiceError>, JoinError> {
+    let data = ctx.data.read().await;
+    let history_lock = data.get::<RollHistory>().expect("roll history not found");
+
+    let mut record = RollRecord {
+        user: user.id.0,
+        user_name: user.name.clone(),
+        channel: channel.0,
+        kind,
+        expression: expr.trim().to_owned(),
+        result: String::new(),
+        dice: vec![],
+        timestamp: Utc::now().timestamp(),
+        seeded: false,
+        hidden,
+    };
+
+    let session = history_lock
+        .read()
+        .await
+        .sessions
+        .get(&channel.0)
+        .filter(|s| !s.finished)
+        .map(|s| Arc::clone(&s.rolls));
+
+    let res = match session {
+        Some(session) => {
+            let mut session = session.lock_owned().await;
+            // past the limit the rolls could no longer be verified, so refuse them rather than lose them
+            if session.rolls.len() >= MAX_SESSION_ROLLS {
+                return Ok(Err(DiceError::SessionFull(MAX_SESSION_ROLLS)));
+            }
+
+            let to_roll = record.expression.clone();
+            let (res, mut session) = tokio::task::spawn_blocking(move || {
+                let res = kind.roll(&to_roll, &mut session.rng);
+                (res, session)
+            })
+            .await?;
+
+            record.seeded = true;
+            if let Ok(rolled) = &res {
+                record.result = rolled.result.clone();
+                record.dice = rolled.dice.clone();
+            }
+            session.rolls.push((record.clone(), res.is_err()));
+            res
+        }
+        None => {
+            let to_roll = record.expression.clone();
+            tokio::task::spawn_blocking(move || kind.roll(&to_roll, &mut ThreadRng::default())).await?
+        }
+    };
+
+    let rolled = match res {
+        Ok(rolled) => rolled,
+        Err(why) => return Ok(Err(why)),
+    };
+
+    record.result = rolled.result;
+    record.dice = rolled.dice;
+
+    if let Err(why) = append_to_file(&record).await {
+        eprintln!("Could not write roll to {}: {:?}", HISTORY_FILE, why);
+    }
+    history_lock.write().await.record(record);
+
+    Ok(Ok(rolled.reply))
+}
+
+/// Replays a session's rolls with the given seed, returning the first roll which does not match
+fn verify_session(seed: u64, rolls: &[(RollRecord, bool)]) -> Option<usize> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    rolls.iter().position(|(record, failed)| {
+        match record.kind.roll(&record.expression, &mut rng) {
+            Ok(rolled) => *failed || rolled.dice != record.dice || rolled.result != record.result,
+            Err(_) => !*failed,
+        }
+    })
+}
+
+#[group]
+#[prefixes("rolls")]
+#[default_command(roll_history)]
+#[commands(roll_history, start_seeded, end_seeded, verify_seeded)]
+/// A group of commands for reviewing the rolls made in a channel
+struct Rolls;
+
+#[command("history")]
+/// Show the most recent rolls in this channel, optionally only those by a specific user
+/// Shows 10 rolls unless told otherwise, and at most 50
+#[example = "@Major 20"]
+#[example = "5"]
+async fn roll_history(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut user = None;
+    let mut count = DEFAULT_HISTORY_LEN;
+    for arg in args.raw() {
+        if let Ok(num) = arg.parse::<usize>() {
+            count = num.min(MAX_HISTORY_LEN);
+        } else if let Ok(id) = arg.parse::<UserId>() {
+            user = Some(id);
+        } else {
+            reply!(ctx, msg, format!("{} is neither a user nor a number of rolls", arg));
+            return Ok(());
+        }
+    }
+
+    let data = ctx.data.read().await;
+    let history_lock = data.get::<RollHistory>().expect("roll history not found");
+    let recent = history_lock.read().await.recent(msg.channel_id, user, count);
+
+    if recent.is_empty() {
+        reply!(ctx, msg, "There are no recorded rolls to show");
+        return Ok(());
+    }
+
+    long_say!(ctx, msg, recent, "\n");
+    Ok(())
+}
+
+#[command("seed")]
+/// Start a seeded session in this channel, every roll made here will come from the seed until it is ended
+/// A random seed is used if none is given, and it is sent to you privately
+/// Once the session ends the seed is revealed and anyone can check the rolls against it
+#[example = "1234567"]
+async fn start_seeded(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let seed = if args.is_empty() {
+        ThreadRng::default().gen::<u64>()
+    } else {
+        match args.single::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => {
+                reply!(ctx, msg, "The seed must be a positive whole number");
+                return Ok(());
+            }
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let history_lock = data.get::<RollHistory>().expect("roll history not found");
+    let mut history = history_lock.write().await;
+
+    if history.sessions.get(&msg.channel_id.0).is_some_and(|s| !s.finished) {
+        reply!(ctx, msg, "This channel already has a seeded session, it must be ended first");
+        return Ok(());
+    }
+
+    let dm_res = msg
+        .author
+        .dm(ctx, |m| {
+            m.content(format!(
+                "The seed for your session in <#{}> is {}",
+                msg.channel_id.0, seed
+            ))
+        })
+        .await;
+
+    if let Err(why) = dm_res {
+        eprintln!("Could not DM the seed of a session: {:?}", why);
+        reply!(ctx, msg, "I could not DM you the seed, so the session was not started");
+        return Ok(());
+    }
+
+    history.sessions.insert(
+        msg.channel_id.0,
+        SeededSession {
+            seed,
+            started_by: msg.author.id.0,
+            finished: false,
+            rolls: Arc::new(Mutex::new(SessionRolls {
+                rng: StdRng::seed_from_u64(seed),
+                rolls: vec![],
+            })),
+        },
+    );
+
+    reply!(
+        ctx,
+        msg,
+        "Started a seeded session, all rolls in this channel will use it until it is ended"
+    );
+    Ok(())
+}
+
+#[command("unseed")]
+#[aliases("endseed")]
+/// End this channel's seeded session and reveal its seed
+/// Only the user who started it or a bot admin may end it
+async fn end_seeded(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let config = data.get::<BotData>().expect("could not get config");
+    let history_lock = data.get::<RollHistory>().expect("roll history not found");
+    let mut history = history_lock.write().await;
+
+    let session = match history.sessions.get_mut(&msg.channel_id.0).filter(|s| !s.finished) {
+        Some(session) => session,
+        None => {
+            reply!(ctx, msg, "This channel does not have a seeded session");
+            return Ok(());
+        }
+    };
+
+    let author = msg.author.id.0;
+    if author != session.started_by && author != config.owner && !config.admins.contains(&author) {
+        reply!(ctx, msg, "Only the user who started the session or a bot admin may end it");
+        return Ok(());
+    }
+
+    session.finished = true;
+    let count = session.rolls.lock().await.rolls.len();
+    reply!(
+        ctx,
+        msg,
+        format!(
+            "Ended the seeded session after {} rolls, the seed was {}\nAnyone may now check the rolls with `rolls verify {}`",
+            count,
+            session.seed,
+            session.seed
+        )
+    );
+    Ok(())
+}
+
+#[command("verify")]
+/// Replay the rolls of this channel's seeded session with the given seed to check they match
+#[example = "1234567"]
+async fn verify_seeded(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let seed = match args.single::<u64>() {
+        Ok(seed) => seed,
+        Err(_) => {
+            reply!(ctx, msg, "You must supply the seed to check the rolls against");
+            return Ok(());
+        }
+    };
+
+    let rolls = {
+        let data = ctx.data.read().await;
+        let history_lock = data.get::<RollHistory>().expect("roll history not found");
+        let history = history_lock.read().await;
+        match history.sessions.get(&msg.channel_id.0) {
+            Some(session) => session.rolls.lock().await.rolls.clone(),
+            None => {
+                reply!(ctx, msg, "This channel has not had a seeded session");
+                return Ok(());
+            }
+        }
+    };
+
+    let count = rolls.len();
+    let mismatch = tokio::task::spawn_blocking(move || {
+        verify_session(seed, &rolls).map(|index| (index, rolls[index].0.to_string()))
+    })
+    .await?;
+
+    match mismatch {
+        Some((index, record)) => reply!(
+            ctx,
+            msg,
+            format!("Roll {} of the session does not match that seed:\n{}", index + 1, record)
+        ),
+        None => reply!(ctx, msg, format!("All {} rolls of the session match that seed", count)),
+    }
+    Ok(())
+}
@@ -0,0 +1,479 @@
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::ThreadRng,
+    Rng,
+};
+use itertools::Itertools;
+use serenity::{
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::channel::Message,
+    prelude::*,
+};
+
+use std::borrow::Cow;
+
+mod expr;
+pub(crate) mod gm;
+pub(crate) mod history;
+mod odds;
+mod parser;
+mod pool;
+pub(crate) mod roll_macros;
+pub(crate) mod stats;
+
+pub(crate) use expr::Expr;
+use expr::RollState;
+use history::RollKind;
+pub use pool::PoolResult;
+
+/// The most dice that may be rolled by a single expression
+pub(crate) const MAX_DICE: usize = u16::MAX as usize;
+
+#[derive(Debug)]
+pub enum DiceError {
+    Parse {
+        expr: String,
+        pos: usize,
+        reason: Cow<'static, str>,
+    },
+    TooManyDice,
+    TooComplex,
+    DivideByZero,
+    Overflow,
+    /// The channel's seeded session cannot record any more rolls
+    SessionFull(usize),
+}
+
+impl DiceError {
+    pub(crate) fn parse<T: Into<Cow<'static, str>>>(expr: &str, pos: usize, reason: T) -> DiceError {
+        DiceError::Parse {
+            expr: expr.to_owned(),
+            pos,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for DiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiceError::Parse { expr, pos, reason } => {
+                // point at the character that broke the expression, counting chars not bytes
+                let offset = expr[..*pos].chars().count();
+                write!(
+                    f,
+                    "Could not parse that roll, {} at position {}:\n```\n{}\n{:>width$}\n```",
+                    reason,
+                    offset + 1,
+                    expr,
+                    "^",
+                    width = offset + 1
+                )
+            }
+            DiceError::TooManyDice => write!(f, "You tried to roll way too many dice"),
+            DiceError::TooComplex => write!(f, "That roll has too many possible results to calculate"),
+            DiceError::DivideByZero => write!(f, "That roll tried to divide by zero"),
+            DiceError::Overflow => write!(f, "The result of that roll was too large to calculate"),
+            DiceError::SessionFull(max) => write!(
+                f,
+                "This channel's seeded session has reached its limit of {} rolls, end it with `rolls unseed` to keep rolling",
+                max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DiceError {}
+
+pub struct RollResult {
+    pub total: i64,
+    pub breakdown: String,
+    pub rolls: Vec<i16>,
+}
+
+impl std::fmt::Display for RollResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.breakdown.len() > 1850 {
+            write!(
+                f,
+                "You rolled: {}\n[There were too many die rolls to show the result of each one]",
+                self.total
+            )
+        } else {
+            write!(f, "You rolled: {}\n{}", self.total, self.breakdown)
+        }
+    }
+}
+
+pub struct DieRoll;
+
+impl DieRoll {
+    pub fn roll_dice(to_roll: &str, reroll: bool) -> Result<RollResult, DiceError> {
+        let mut rng = ThreadRng::default();
+        DieRoll::roll_dice_with(to_roll, reroll, &mut rng)
+    }
+
+    /// Roll an expression using the given source of randomness, such as a seeded one
+    pub fn roll_dice_with<R: Rng>(to_roll: &str, reroll: bool, rng: &mut R) -> Result<RollResult, DiceError> {
+        let parsed = parser::parse(to_roll)?;
+        DieRoll::roll_expr(&parsed, reroll, rng)
+    }
+
+    pub(crate) fn roll_expr<R: Rng>(parsed: &Expr, reroll: bool, rng: &mut R) -> Result<RollResult, DiceError> {
+        let mut state = RollState::new(rng, reroll);
+        let total = parsed.roll(&mut state)?;
+        Ok(RollResult {
+            total,
+            breakdown: state.breakdown,
+            rolls: state.rolls,
+        })
+    }
+
+    /// Check that an expression could be rolled without rolling it
+    pub fn validate(to_roll: &str) -> Result<(), DiceError> {
+        parser::parse(to_roll).map(|_| ())
+    }
+
+    /// Calculate the exact chance of every result of an expression
+    pub fn odds(to_roll: &str, reroll: bool) -> Result<odds::Distribution, DiceError> {
+        let parsed = parser::parse(to_roll)?;
+        odds::distribution(&parsed, reroll)
+    }
+
+    /// Roll a pool of dice, counting the number which meet the target instead of summing them
+    pub fn roll_pool_with<R: Rng>(to_roll: &str, reroll: bool, rng: &mut R) -> Result<PoolResult, DiceError> {
+        let pool = parser::parse_pool(to_roll)?;
+        let mut state = RollState::new(rng, reroll);
+        pool.roll(&mut state)
+    }
+
+    /// Combine a pool with a target and a number of extra dice given separately, such as by a slash command
+    pub fn build_pool(to_roll: &str, target: Option<i64>, extra: Option<i64>) -> Result<String, DiceError> {
+        let mut pool = parser::parse_pool(to_roll)?;
+
+        if let Some(target) = target {
+            if let Some(pos) = to_roll.find('>') {
+                return Err(DiceError::parse(to_roll, pos, "the pool already has a target, give it only once"));
+            }
+            if target < 1 || target > pool.sides as i64 {
+                return Err(DiceError::parse(
+                    to_roll,
+                    to_roll.len(),
+                    format!("a target of {} can never be hit on a d{}", target, pool.sides),
+                ));
+            }
+            pool.target = target as i16;
+        }
+
+        if let Some(extra) = extra {
+            let total = pool.count as i64 + extra;
+            if total < 1 {
+                return Err(DiceError::parse(
+                    to_roll,
+                    to_roll.len(),
+                    format!("{:+} dice would leave nothing to roll", extra),
+                ));
+            }
+            if total as usize >= MAX_DICE {
+                return Err(DiceError::TooManyDice);
+            }
+            pool.count = total as u16;
+        }
+
+        Ok(pool.to_string())
+    }
+}
+
+#[group]
+#[commands(roll, reroll, secret_roll, pool, repool, odds, roll_stats, shuffle)]
+/// A group of commands related to rolling dice
+struct Dice;
+
+async fn perform_roll(ctx: &Context, msg: &Message, to_roll: &str, reroll: bool, hidden: bool) {
+    let owned_to_roll = roll_macros::expand_macro(ctx, msg.author.id, to_roll).await;
+    let kind = if reroll { RollKind::Reroll } else { RollKind::Roll };
+    let res = history::roll_and_record(
+        ctx,
+        &msg.author,
+        msg.channel_id,
+        kind,
+        owned_to_roll.clone(),
+        hidden,
+    )
+    .await;
+
+    match res {
+        Ok(Ok(val)) if hidden => {
+            let note = gm::deliver_secret_roll(
+                ctx,
+                &msg.author,
+                msg.guild_id,
+                msg.channel_id,
+                &owned_to_roll,
+                &val,
+            )
+            .await;
+            reply!(ctx, msg, note);
+        }
+        Ok(Ok(val)) => reply!(ctx, msg, val),
+        Ok(Err(why)) => reply!(ctx, msg, why),
+        Err(why) => {
+            eprintln!("Error occurred trying to roll dice... {}\n{:?}", to_roll, why);
+            reply!(ctx, msg, "Error occurred, you probably tried to roll way too many dice");
+        }
+    }
+}
+
+async fn perform_pool(ctx: &Context, msg: &Message, to_roll: &str, reroll: bool) {
+    let kind = if reroll { RollKind::Repool } else { RollKind::Pool };
+    let res = history::roll_and_record(ctx, &msg.author, msg.channel_id, kind, to_roll.to_owned(), false).await;
+
+    match res {
+        Ok(Ok(val)) => reply!(ctx, msg, val),
+        Ok(Err(why)) => reply!(ctx, msg, why),
+        Err(why) => {
+            eprintln!("Error occurred trying to roll a pool... {}\n{:?}", to_roll, why);
+            reply!(ctx, msg, "Error occurred, you probably tried to roll way too many dice");
+        }
+    }
+}
+
+#[command]
+/// Roll a pool of dice and count the successes, by default a success is a 5 or higher on a d6
+/// Use >=N to change the target number, ! to have dice that roll their max add another die, and +N to add extra dice
+#[example("6d6>=5")]
+#[example("6")]
+#[example("8d10>=7")]
+#[example("6d6! +2")]
+async fn pool(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(
+            ctx,
+            msg,
+            "You must supply a number of dice to roll"
+        );
+        return Ok(());
+    }
+    perform_pool(ctx, msg, args.rest(), false).await;
+    Ok(())
+}
+
+#[command]
+/// Same as the pool command, except 1's and 2's will be re-rolled once, keeping the higher result
+async fn repool(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(
+            ctx,
+            msg,
+            "You must supply a number of dice to roll"
+        );
+        return Ok(());
+    }
+    perform_pool(ctx, msg, args.rest(), true).await;
+    Ok(())
+}
+
+#[command]
+/// Same as the roll command, except 1's and 2's will be re-rolled once, keeping the higher result
+/// The name of one of your saved macros may be given instead of a roll
+async fn reroll(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(
+            ctx,
+            msg,
+            "You must supply a number of dice to roll"
+        );
+        return Ok(());
+    }
+    perform_roll(ctx, msg, args.rest(), true, false).await;
+    Ok(())
+}
+
+#[command]
+#[aliases("r")]
+/// Roll a number of dice, using the format XdY where X is the number of dice, and Y is the number of sides on the die to roll
+/// Rolls may be added, subtracted, multiplied, divided and grouped with parentheses
+/// Append khN/klN to keep the highest/lowest N dice, dhN/dlN to drop them, or ! to explode dice
+/// The name of one of your saved macros may be given instead of a roll
+#[example("1d20")]
+#[example("2d6-1")]
+#[example("(1d8+2)*2")]
+#[example("4d6kh3")]
+#[example("sword")]
+pub(crate) async fn roll(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(
+            ctx,
+            msg,
+            "You must supply a number of dice to roll"
+        );
+        return Ok(());
+    }
+    perform_roll(ctx, msg, args.rest(), false, false).await;
+    Ok(())
+}
+
+#[command("groll")]
+#[aliases("proll")]
+/// Same as the roll command, except the result is DMed to you and this channel's GM instead of being posted
+/// Use the gm command to set who the GM is for a channel
+#[example("1d20+4")]
+async fn secret_roll(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(
+            ctx,
+            msg,
+            "You must supply a number of dice to roll"
+        );
+        return Ok(());
+    }
+    perform_roll(ctx, msg, args.rest(), false, true).await;
+    Ok(())
+}
+
+#[command]
+/// Calculate the exact odds of a roll, along with the chance to meet a target if one is given after >=
+/// Start with reroll to use the same rule as the reroll command
+#[example("3d6+2 >= 12")]
+#[example("4d6kh3")]
+#[example("reroll 2d8 >= 10")]
+async fn odds(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(
+            ctx,
+            msg,
+            "You must supply a roll to calculate the odds of"
+        );
+        return Ok(());
+    }
+
+    let mut to_calc = args.rest().trim();
+    let reroll = to_calc.get(..6).is_some_and(|start| start.eq_ignore_ascii_case("reroll"));
+    if reroll {
+        to_calc = &to_calc[6..];
+    }
+
+    let mut parts = to_calc.splitn(2, ">=");
+    let expr = parts.next().unwrap_or_default().to_owned();
+    let target = match parts.next().map(|t| t.trim().parse::<i64>()) {
+        Some(Ok(target)) => Some(target),
+        Some(Err(_)) => {
+            reply!(ctx, msg, "The target after >= must be a whole number");
+            return Ok(());
+        }
+        None => None,
+    };
+
+    let typing_fut = msg.channel_id.broadcast_typing(ctx);
+    let odds_handle = tokio::task::spawn_blocking(move || {
+        DieRoll::odds(&expr, reroll).map(|dist| dist.summary(&expr, target))
+    });
+
+    let (_, odds_res) = tokio::join!(typing_fut, odds_handle);
+
+    match odds_res? {
+        Ok(summary) => reply!(ctx, msg, summary),
+        Err(why) => reply!(ctx, msg, why),
+    }
+
+    Ok(())
+}
+
+#[command("rollstats")]
+/// Generate character stats with one of several methods, 4d6 by default
+/// 4d6: roll 4d6 and drop the lowest for each of the six D&D 5e stats
+/// array: the D&D 5e standard array
+/// pointbuy: check that six scores from 8 to 15 fit into D&D 5e's 27 point buy
+/// bnb: check a Mind, Body, and Spirit allocation, or randomly allocate one if no values are given
+#[example("4d6")]
+#[example("pointbuy 15 14 13 10 10 8")]
+#[example("bnb 2 3 1")]
+pub(crate) async fn roll_stats(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let method = args.single::<String>().ok();
+    let reply = stats::generate_stats(ctx, &msg.author, msg.channel_id, method.as_deref(), args.rest()).await;
+    reply!(ctx, msg, reply);
+    Ok(())
+}
+
+pub(crate) fn perform_shuffle(size: usize) -> Cow<'static, str> {
+    if size < 2 {
+        return Cow::Borrowed("Cannot shuffle a number less than 2");
+    }
+
+    if size > 64 {
+        return Cow::Borrowed("Cowardly refusing to shuffle a number greater than 64");
+    }
+
+    let mut list: Vec<usize> = Vec::with_capacity(size);
+
+    for num in 1..(size + 1) {
+        list.push(num);
+    }
+
+    let shuffler = Uniform::from(0..size);
+    let mut rng = ThreadRng::default();
+    for index in 0..list.len() {
+        let rand_index = shuffler.sample(&mut rng);
+        list.swap(rand_index, index);
+    }
+
+    Cow::Owned(format!("{}",list.iter().format(", ")))
+
+}
+
+#[command]
+/// shuffle a series of numbers from 1 to the given argument (inclusive)
+#[example = "20"]
+pub(crate) async fn shuffle(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(
+            ctx,
+            msg,
+            "You must supply a number of numbers to shuffle"
+        );
+        return Ok(());
+    }
+
+    let size = match args.single::<usize>() {
+        Ok(size) => size,
+        Err(_) => {
+            reply!(
+                ctx,
+                msg,
+                "An invalid number was provided"
+            );
+            return Ok(());
+        }
+    };
+
+    let typing_fut = msg.channel_id.broadcast_typing(ctx);
+    let shuffle_handle = tokio::task::spawn_blocking(move || {
+        perform_shuffle(size)
+    });
+
+    let (_, shuffle_res) = tokio::join!(typing_fut, shuffle_handle);
+
+    reply!(ctx, msg, shuffle_res?);
+
+    Ok(())
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_pools_from_separate_options() {
+        assert_eq!(DieRoll::build_pool("6", None, None).unwrap(), "6d6>=5");
+        assert_eq!(DieRoll::build_pool("4d10!", Some(8), Some(2)).unwrap(), "6d10!>=8");
+        assert_eq!(DieRoll::build_pool("5d6", None, Some(-2)).unwrap(), "3d6>=5");
+    }
+
+    #[test]
+    fn refuses_bad_pool_options() {
+        assert!(DieRoll::build_pool("5d6>=4", Some(5), None).is_err());
+        assert!(DieRoll::build_pool("5d6", Some(7), None).is_err());
+        assert!(DieRoll::build_pool("2d6", None, Some(-2)).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use super::{
+    expr::{BinOp, DiceTerm, Expr, Keep, REROLL_THRESHOLD},
+    DiceError,
+};
+
+/// The most distinct results a distribution may have before it is considered too complex
+const MAX_OUTCOMES: usize = 100_000;
+
+/// The most pairs of outcomes that may be combined by a single operation
+const MAX_WORK: usize = 50_000_000;
+
+/// Exploding dice can explode forever, stop once a chain becomes less likely than this
+const EXPLODE_CUTOFF: f64 = 1e-12;
+
+const PERCENTILES: &[u8] = &[10, 25, 50, 75, 90];
+
+/// The exact probability of every possible result of a dice expression
+pub struct Distribution(BTreeMap<i64, f64>);
+
+impl Distribution {
+    fn constant(val: i64) -> Distribution {
+        let mut map = BTreeMap::new();
+        map.insert(val, 1.0);
+        Distribution(map)
+    }
+
+    fn from_outcomes(outcomes: BTreeMap<i64, f64>) -> Result<Distribution, DiceError> {
+        if outcomes.len() > MAX_OUTCOMES {
+            return Err(DiceError::TooComplex);
+        }
+        Ok(Distribution(outcomes))
+    }
+
+    fn combine(&self, other: &Distribution, op: BinOp) -> Result<Distribution, DiceError> {
+        if self.0.len().saturating_mul(other.0.len()) > MAX_WORK {
+            return Err(DiceError::TooComplex);
+        }
+
+        let mut outcomes = BTreeMap::new();
+        for (lhs, lhs_prob) in &self.0 {
+            for (rhs, rhs_prob) in &other.0 {
+                let val = match op {
+                    BinOp::Add => lhs.checked_add(*rhs),
+                    BinOp::Sub => lhs.checked_sub(*rhs),
+                    BinOp::Mul => lhs.checked_mul(*rhs),
+                    BinOp::Div => {
+                        if *rhs == 0 {
+                            return Err(DiceError::DivideByZero);
+                        }
+                        lhs.checked_div(*rhs).map(|quot| {
+                            if lhs % rhs != 0 && (*lhs < 0) != (*rhs < 0) {
+                                quot - 1
+                            } else {
+                                quot
+                            }
+                        })
+                    }
+                }
+                .ok_or(DiceError::Overflow)?;
+                *outcomes.entry(val).or_insert(0.0) += lhs_prob * rhs_prob;
+            }
+        }
+        Distribution::from_outcomes(outcomes)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.0.iter().map(|(val, prob)| *val as f64 * prob).sum()
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        let variance: f64 = self
+            .0
+            .iter()
+            .map(|(val, prob)| (*val as f64 - mean).powi(2) * prob)
+            .sum();
+        variance.sqrt()
+    }
+
+    /// The lowest result which at least the given percent of rolls are at or below
+    pub fn percentile(&self, percent: u8) -> i64 {
+        let wanted = percent as f64 / 100.0;
+        let mut cumulative = 0.0;
+        for (val, prob) in &self.0 {
+            cumulative += prob;
+            if cumulative >= wanted - 1e-9 {
+                return *val;
+            }
+        }
+        self.max()
+    }
+
+    pub fn min(&self) -> i64 {
+        self.0.keys().next().copied().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> i64 {
+        self.0.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// The chance of rolling the target or higher
+    pub fn chance_at_least(&self, target: i64) -> f64 {
+        self.0.range(target..).fold(0.0, |acc, (_, prob)| acc + prob)
+    }
+}
+
+/// The chance of a single roll of a die showing each face, taking re-rolls into account
+fn single_roll(sides: i16, reroll: bool) -> Vec<(i64, f64)> {
+    let sides_f = sides as f64;
+    (1..=sides)
+        .map(|face| {
+            let prob = if !reroll {
+                1.0 / sides_f
+            } else {
+                // either the first roll was kept, or it was re-rolled and this face was the higher
+                let kept = if face > REROLL_THRESHOLD { 1.0 / sides_f } else { 0.0 };
+                let rerolled: f64 = (1..=REROLL_THRESHOLD.min(sides))
+                    .map(|first| {
+                        let higher = match face.cmp(&first) {
+                            std::cmp::Ordering::Less => 0.0,
+                            std::cmp::Ordering::Equal => first as f64 / sides_f,
+                            std::cmp::Ordering::Greater => 1.0 / sides_f,
+                        };
+                        higher / sides_f
+                    })
+                    .sum();
+                kept + rerolled
+            };
+            (face as i64, prob)
+        })
+        .collect()
+}
+
+/// The chance of a single die totalling each value, including any explosions
+fn single_die(term: &DiceTerm, reroll: bool) -> Vec<(i64, f64)> {
+    let roll = single_roll(term.sides, reroll);
+    if !term.explode {
+        return roll;
+    }
+
+    let max_prob = roll.last().map(|(_, prob)| *prob).unwrap_or(0.0);
+    let mut faces = vec![];
+    let mut chain_prob = 1.0;
+    let mut base = 0;
+    while chain_prob > EXPLODE_CUTOFF {
+        for (face, prob) in roll.iter().take(roll.len() - 1) {
+            faces.push((base + face, chain_prob * prob));
+        }
+        chain_prob *= max_prob;
+        base += term.sides as i64;
+    }
+    faces
+}
+
+/// The chance of exactly `count` successes in `trials` attempts
+fn binomial(trials: usize, count: usize, prob: f64) -> f64 {
+    let mut coefficient = 1.0;
+    for i in 0..count {
+        coefficient = coefficient * (trials - i) as f64 / (i + 1) as f64;
+    }
+    coefficient * prob.powi(count as i32) * (1.0 - prob).powi((trials - count) as i32)
+}
+
+fn dice_distribution(term: &DiceTerm, reroll: bool) -> Result<Distribution, DiceError> {
+    let mut faces = single_die(term, reroll);
+    let count = term.count as usize;
+
+    let (keep, highest) = match term.keep {
+        Some(Keep::Highest(amount)) => (amount as usize, true),
+        Some(Keep::Lowest(amount)) => (amount as usize, false),
+        None => {
+            if faces.len().saturating_mul(count).pow(2) > MAX_WORK.saturating_mul(2) {
+                return Err(DiceError::TooComplex);
+            }
+            // no dice are dropped, so just add up one die at a time using dense arrays
+            // as the faces of a die are always contiguous apart from exploded maximums
+            let low = faces.first().map(|(face, _)| *face).unwrap_or(0);
+            let high = faces.last().map(|(face, _)| *face).unwrap_or(0);
+            let mut single = vec![0.0; (high - low + 1) as usize];
+            for (face, prob) in &faces {
+                single[(face - low) as usize] += prob;
+            }
+
+            let mut total = vec![1.0];
+            for _ in 0..count {
+                let mut next = vec![0.0; total.len() + single.len() - 1];
+                for (offset, total_prob) in total.iter().enumerate() {
+                    for (index, prob) in single.iter().enumerate() {
+                        next[offset + index] += total_prob * prob;
+                    }
+                }
+                total = next;
+            }
+
+            let outcomes = total
+                .into_iter()
+                .enumerate()
+                .filter(|(_, prob)| *prob > 0.0)
+                .map(|(index, prob)| (index as i64 + low * count as i64, prob))
+                .collect();
+            return Distribution::from_outcomes(outcomes);
+        }
+    };
+
+    if faces.len().saturating_mul(count).pow(2).saturating_mul(keep.max(1)) > MAX_WORK {
+        return Err(DiceError::TooComplex);
+    }
+
+    // Walk the faces from the ones being kept first, deciding how many dice show each face.
+    // The first `keep` dice placed are the ones which are kept.
+    if highest {
+        faces.reverse();
+    }
+
+    // states[used] maps the total of the kept dice to its chance
+    let mut states: Vec<BTreeMap<i64, f64>> = vec![BTreeMap::new(); count + 1];
+    states[0].insert(0, 1.0);
+    let mut remaining_prob: f64 = faces.iter().map(|(_, prob)| prob).sum();
+
+    for (face, prob) in &faces {
+        let conditional = if remaining_prob > 0.0 { (prob / remaining_prob).min(1.0) } else { 0.0 };
+        let mut next: Vec<BTreeMap<i64, f64>> = vec![BTreeMap::new(); count + 1];
+        for (used, totals) in states.iter().enumerate() {
+            let left = count - used;
+            for showing in 0..=left {
+                let chance = binomial(left, showing, conditional);
+                if chance == 0.0 {
+                    continue;
+                }
+                let kept = showing.min(keep.saturating_sub(used)) as i64;
+                for (total, total_prob) in totals {
+                    *next[used + showing].entry(total + kept * face).or_insert(0.0) += total_prob * chance;
+                }
+            }
+        }
+        states = next;
+        remaining_prob -= prob;
+    }
+
+    Distribution::from_outcomes(states.swap_remove(count))
+}
+
+/// Calculates the exact distribution of an expression, rather than sampling it
+pub(crate) fn distribution(expr: &Expr, reroll: bool) -> Result<Distribution, DiceError> {
+    match expr {
+        Expr::Number(num) => Ok(Distribution::constant(*num)),
+        Expr::Dice(term) => dice_distribution(term, reroll),
+        Expr::Neg(inner) => {
+            let inner = distribution(inner, reroll)?;
+            Distribution::constant(0).combine(&inner, BinOp::Sub)
+        }
+        Expr::Group(inner) => distribution(inner, reroll),
+        Expr::Binary(op, lhs, rhs) => {
+            let lhs = distribution(lhs, reroll)?;
+            let rhs = distribution(rhs, reroll)?;
+            lhs.combine(&rhs, *op)
+        }
+    }
+}
+
+impl Distribution {
+    pub fn summary(&self, expr: &str, target: Option<i64>) -> String {
+        let mut summary = format!(
+            "```\n{}\nMean: {:.2} | Std. Dev: {:.2} | Min: {} | Max: {}\nPercentiles: ",
+            expr.trim(),
+            self.mean(),
+            self.std_dev(),
+            self.min(),
+            self.max()
+        );
+
+        for (index, percent) in PERCENTILES.iter().enumerate() {
+            if index > 0 {
+                summary.push_str(" | ");
+            }
+            let _ = write!(summary, "{}%: {}", percent, self.percentile(*percent));
+        }
+
+        if let Some(target) = target {
+            let _ = write!(
+                summary,
+                "\nChance of {} or higher: {:.2}%",
+                target,
+                self.chance_at_least(target) * 100.0
+            );
+        }
+
+        summary.push_str("\n```");
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::parser::parse;
+
+    fn odds(expr: &str, reroll: bool) -> Distribution {
+        distribution(&parse(expr).unwrap(), reroll).expect("could not calculate odds")
+    }
+
+    fn assert_close(found: f64, expected: f64) {
+        assert!((found - expected).abs() < 1e-9, "expected {} but found {}", expected, found);
+    }
+
+    #[test]
+    fn two_d6() {
+        let dist = odds("2d6", false);
+        assert_close(dist.0[&7], 6.0 / 36.0);
+        assert_close(dist.0[&2], 1.0 / 36.0);
+        assert_close(dist.0[&12], 1.0 / 36.0);
+        assert_eq!((dist.min(), dist.max()), (2, 12));
+        assert_close(dist.mean(), 7.0);
+        assert_close(dist.std_dev(), (35.0f64 / 6.0).sqrt());
+        assert_close(dist.chance_at_least(10), 6.0 / 36.0);
+        assert_eq!(dist.percentile(50), 7);
+    }
+
+    #[test]
+    fn keeping_dice() {
+        let dist = odds("2d20kh1", false);
+        assert_close(dist.0[&20], 39.0 / 400.0);
+        assert_close(dist.0[&1], 1.0 / 400.0);
+
+        let dist = odds("2d20kl1", false);
+        assert_close(dist.0[&1], 39.0 / 400.0);
+
+        // the well known average of rolling 4d6 and dropping the lowest
+        assert_close(odds("4d6dl1", false).mean(), 15869.0 / 1296.0);
+    }
+
+    #[test]
+    fn arithmetic_on_distributions() {
+        let dist = odds("1d6-1d6", false);
+        assert_close(dist.0[&0], 6.0 / 36.0);
+        assert_close(dist.mean(), 0.0);
+
+        let dist = odds("2*1d4+1", false);
+        assert_eq!(dist.0.keys().copied().collect::<Vec<i64>>(), vec![3, 5, 7, 9]);
+        assert_close(odds("-1d6", false).mean(), -3.5);
+    }
+
+    #[test]
+    fn exploding_dice() {
+        let dist = odds("1d6!", false);
+        assert!(!dist.0.contains_key(&6));
+        assert_close(dist.0[&7], 1.0 / 36.0);
+        assert!((dist.mean() - 4.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rerolling_low_dice() {
+        let dist = odds("1d6", true);
+        assert_close(dist.0.values().sum(), 1.0);
+        assert_close(dist.0[&1], 1.0 / 36.0);
+        assert!(dist.mean() > 3.5);
+    }
+}
@@ -0,0 +1,509 @@
+use std::{borrow::Cow, iter::Peekable, str::CharIndices};
+
+use super::{
+    expr::{BinOp, DiceTerm, Expr, Keep},
+    pool::{DicePool, DEFAULT_POOL_SIDES, DEFAULT_POOL_TARGET},
+    DiceError, MAX_DICE,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token {
+    Number(i64),
+    Dice,
+    KeepHighest,
+    KeepLowest,
+    DropHighest,
+    DropLowest,
+    Explode,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LParen,
+    RParen,
+    AtLeast,
+    Greater,
+    End,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(num) => write!(f, "{}", num),
+            Token::Dice => write!(f, "d"),
+            Token::KeepHighest => write!(f, "kh"),
+            Token::KeepLowest => write!(f, "kl"),
+            Token::DropHighest => write!(f, "dh"),
+            Token::DropLowest => write!(f, "dl"),
+            Token::Explode => write!(f, "!"),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Star => write!(f, "*"),
+            Token::Slash => write!(f, "/"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::AtLeast => write!(f, ">="),
+            Token::Greater => write!(f, ">"),
+            Token::End => write!(f, "end of expression"),
+        }
+    }
+}
+
+/// A token along with the byte offset it started at in the source expression
+type Spanned = (Token, usize);
+
+fn tokenize(expr: &str) -> Result<Vec<Spanned>, DiceError> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = expr.char_indices().peekable();
+
+    while let Some((pos, ch)) = chars.next() {
+        let token = match ch {
+            c if c.is_whitespace() => continue,
+            '0'..='9' => {
+                let mut end = pos + 1;
+                while let Some(&(next_pos, next)) = chars.peek() {
+                    if !next.is_ascii_digit() {
+                        break;
+                    }
+                    end = next_pos + 1;
+                    chars.next();
+                }
+                let num = expr[pos..end]
+                    .parse::<i64>()
+                    .map_err(|_| DiceError::parse(expr, pos, "number is too large"))?;
+                Token::Number(num)
+            }
+            'd' | 'D' => match chars.peek() {
+                Some(&(_, 'h')) | Some(&(_, 'H')) => {
+                    chars.next();
+                    Token::DropHighest
+                }
+                Some(&(_, 'l')) | Some(&(_, 'L')) => {
+                    chars.next();
+                    Token::DropLowest
+                }
+                _ => Token::Dice,
+            },
+            'k' | 'K' => match chars.peek() {
+                Some(&(_, 'h')) | Some(&(_, 'H')) => {
+                    chars.next();
+                    Token::KeepHighest
+                }
+                Some(&(_, 'l')) | Some(&(_, 'L')) => {
+                    chars.next();
+                    Token::KeepLowest
+                }
+                _ => Token::KeepHighest,
+            },
+            '!' => Token::Explode,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' | 'x' | 'X' => Token::Star,
+            '/' => Token::Slash,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '>' => match chars.peek() {
+                Some(&(_, '=')) => {
+                    chars.next();
+                    Token::AtLeast
+                }
+                _ => Token::Greater,
+            },
+            _ => {
+                return Err(DiceError::parse(
+                    expr,
+                    pos,
+                    format!("unexpected character '{}'", ch),
+                ))
+            }
+        };
+        tokens.push((token, pos));
+    }
+
+    tokens.push((Token::End, expr.len()));
+    Ok(tokens)
+}
+
+/// A recursive descent parser over the grammar:
+/// ```text
+/// expr    := term (('+' | '-') term)*
+/// term    := unary (('*' | '/') unary)*
+/// unary   := '-' unary | primary
+/// primary := NUMBER | NUMBER? dice | '(' expr ')'
+/// dice    := 'd' NUMBER '!'? (('kh' | 'kl' | 'dh' | 'dl') NUMBER?)?
+/// ```
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Spanned>,
+    index: usize,
+    dice_count: u32,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Spanned {
+        self.tokens[self.index]
+    }
+
+    fn advance(&mut self) -> Spanned {
+        let token = self.tokens[self.index];
+        if token.0 != Token::End {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn error<T: Into<Cow<'static, str>>>(&self, pos: usize, reason: T) -> DiceError {
+        DiceError::parse(self.source, pos, reason)
+    }
+
+    fn expect_number(&mut self, what: &str) -> Result<(i64, usize), DiceError> {
+        match self.advance() {
+            (Token::Number(num), pos) => Ok((num, pos)),
+            (Token::End, pos) => Err(self.error(pos, format!("expected {}", what))),
+            (token, pos) => Err(self.error(pos, format!("expected {} but found '{}'", what, token))),
+        }
+    }
+
+    fn unexpected(&self, (token, pos): Spanned) -> DiceError {
+        match token {
+            Token::End => self.error(pos, "unexpected end of expression"),
+            _ => self.error(pos, format!("unexpected '{}'", token)),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, DiceError> {
+        let mut lhs = self.parse_term()?;
+        loop {
+            let op = match self.peek().0 {
+                Token::Plus => BinOp::Add,
+                Token::Minus => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.parse_term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, DiceError> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek().0 {
+                Token::Star => BinOp::Mul,
+                Token::Slash => BinOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.parse_unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, DiceError> {
+        if self.peek().0 == Token::Minus {
+            self.advance();
+            let inner = self.parse_unary()?;
+            return Ok(Expr::Neg(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, DiceError> {
+        let spanned = self.advance();
+        match spanned.0 {
+            Token::Number(num) => {
+                if self.peek().0 == Token::Dice {
+                    self.parse_dice(Some(num), spanned.1)
+                } else {
+                    Ok(Expr::Number(num))
+                }
+            }
+            Token::Dice => {
+                // step back so parse_dice sees the 'd' just like it would after a count
+                self.index -= 1;
+                self.parse_dice(None, spanned.1)
+            }
+            Token::LParen => {
+                let inner = self.parse_expr()?;
+                let closing = self.advance();
+                if closing.0 != Token::RParen {
+                    return Err(match closing.0 {
+                        Token::End => self.error(spanned.1, "unmatched '('"),
+                        _ => self.error(closing.1, format!("expected ')' but found '{}'", closing.0)),
+                    });
+                }
+                Ok(Expr::Group(Box::new(inner)))
+            }
+            _ => Err(self.unexpected(spanned)),
+        }
+    }
+
+    fn parse_dice(&mut self, count: Option<i64>, start: usize) -> Result<Expr, DiceError> {
+        let count = count.unwrap_or(1);
+        if count < 1 {
+            return Err(self.error(start, "must roll at least one die"));
+        }
+
+        // consume the 'd'
+        self.advance();
+
+        let sides = match self.advance() {
+            (Token::Number(sides), _) if sides >= 1 && sides <= i16::MAX as i64 => sides,
+            (Token::Number(_), pos) => {
+                return Err(self.error(pos, format!("a die must have between 1 and {} sides", i16::MAX)))
+            }
+            other => {
+                return Err(match other.0 {
+                    Token::End => self.error(other.1, "expected the number of sides on the die"),
+                    _ => self.error(other.1, format!("expected the number of sides on the die but found '{}'", other.0)),
+                })
+            }
+        };
+
+        self.dice_count = self.dice_count.saturating_add(count.min(u32::MAX as i64) as u32);
+        if self.dice_count as usize >= MAX_DICE {
+            return Err(DiceError::TooManyDice);
+        }
+
+        let mut term = DiceTerm {
+            count: count as u16,
+            sides: sides as i16,
+            keep: None,
+            explode: false,
+        };
+
+        loop {
+            let (token, pos) = self.peek();
+            match token {
+                Token::Explode => {
+                    self.advance();
+                    if term.explode {
+                        return Err(self.error(pos, "dice can only explode once"));
+                    }
+                    if term.sides == 1 {
+                        return Err(self.error(pos, "cannot explode a die with only one side"));
+                    }
+                    term.explode = true;
+                }
+                Token::KeepHighest | Token::KeepLowest | Token::DropHighest | Token::DropLowest => {
+                    self.advance();
+                    if term.keep.is_some() {
+                        return Err(self.error(pos, "only one keep or drop modifier is allowed per roll"));
+                    }
+                    let amount = match self.peek() {
+                        (Token::Number(num), num_pos) => {
+                            self.advance();
+                            if num > term.count as i64 {
+                                return Err(self.error(num_pos, format!("cannot keep or drop {} of only {} dice", num, term.count)));
+                            }
+                            num as u16
+                        }
+                        _ => 1,
+                    };
+                    term.keep = Some(match token {
+                        Token::KeepHighest => Keep::Highest(amount),
+                        Token::KeepLowest => Keep::Lowest(amount),
+                        Token::DropHighest => Keep::Lowest(term.count - amount),
+                        _ => Keep::Highest(term.count - amount),
+                    });
+                }
+                _ => break,
+            }
+        }
+
+        Ok(Expr::Dice(term))
+    }
+}
+
+/// Parse a dice expression into an [`Expr`] which can then be rolled any number of times
+pub(crate) fn parse(expr: &str) -> Result<Expr, DiceError> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser {
+        source: expr,
+        tokens,
+        index: 0,
+        dice_count: 0,
+    };
+
+    if parser.peek().0 == Token::End {
+        return Err(parser.error(0, "the expression is empty"));
+    }
+
+    let parsed = parser.parse_expr()?;
+    let leftover = parser.peek();
+    if leftover.0 != Token::End {
+        return Err(parser.unexpected(leftover));
+    }
+    Ok(parsed)
+}
+
+/// Parse a success counting pool in the form `N[dS][!][>=T][+E]`, `N` being the number of dice,
+/// `S` the sides on each die, `T` the target number for a success, and `E` any extra dice to add
+pub(crate) fn parse_pool(expr: &str) -> Result<DicePool, DiceError> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser {
+        source: expr,
+        tokens,
+        index: 0,
+        dice_count: 0,
+    };
+
+    let (count, count_pos) = match parser.peek() {
+        (Token::Dice, pos) => (1, pos),
+        _ => parser.expect_number("the number of dice in the pool")?,
+    };
+
+    let mut pool = DicePool {
+        count: 0,
+        sides: DEFAULT_POOL_SIDES,
+        target: DEFAULT_POOL_TARGET,
+        explode: false,
+    };
+
+    if parser.peek().0 == Token::Dice {
+        parser.advance();
+        let (sides, pos) = parser.expect_number("the number of sides on the die")?;
+        if sides < 1 || sides > i16::MAX as i64 {
+            return Err(parser.error(pos, format!("a die must have between 1 and {} sides", i16::MAX)));
+        }
+        pool.sides = sides as i16;
+    }
+
+    if let (Token::Explode, pos) = parser.peek() {
+        parser.advance();
+        if pool.sides == 1 {
+            return Err(parser.error(pos, "cannot explode a die with only one side"));
+        }
+        pool.explode = true;
+    }
+
+    let comparison = parser.peek();
+    if comparison.0 == Token::AtLeast || comparison.0 == Token::Greater {
+        parser.advance();
+        let (target, pos) = parser.expect_number("a target number")?;
+        let target = if comparison.0 == Token::Greater { target + 1 } else { target };
+        if target < 1 || target > pool.sides as i64 {
+            return Err(parser.error(pos, format!("a target of {} can never be hit on a d{}", target, pool.sides)));
+        }
+        pool.target = target as i16;
+    } else if pool.target > pool.sides {
+        // the default target is only sensible for d6 pools
+        pool.target = pool.sides;
+    }
+
+    let mut extra = 0;
+    if parser.peek().0 == Token::Plus {
+        parser.advance();
+        extra = parser.expect_number("a number of extra dice")?.0;
+    }
+
+    let leftover = parser.peek();
+    if leftover.0 != Token::End {
+        return Err(parser.unexpected(leftover));
+    }
+
+    let total = count.saturating_add(extra);
+    if total < 1 {
+        return Err(parser.error(count_pos, "must roll at least one die"));
+    }
+    if total as usize >= MAX_DICE {
+        return Err(DiceError::TooManyDice);
+    }
+    pool.count = total as u16;
+
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::dice::DieRoll;
+
+    fn total(expr: &str) -> i64 {
+        let mut rng = StdRng::seed_from_u64(0);
+        DieRoll::roll_dice_with(expr, false, &mut rng).expect("expression did not roll").total
+    }
+
+    /// The byte offset and reason of a parse error
+    fn parse_error(expr: &str) -> (usize, String) {
+        match parse(expr) {
+            Err(DiceError::Parse { pos, reason, .. }) => (pos, reason.into_owned()),
+            Err(other) => panic!("{} failed with {} instead of a parse error", expr, other),
+            Ok(_) => panic!("{} parsed", expr),
+        }
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(total("2+3*4"), 14);
+        assert_eq!(total("2*3+4"), 10);
+        assert_eq!(total("10-4/2"), 8);
+        assert_eq!(total("(2+3)*4"), 20);
+    }
+
+    #[test]
+    fn operators_are_left_associative() {
+        assert_eq!(total("10-4-3"), 3);
+        assert_eq!(total("24/4/2"), 3);
+    }
+
+    #[test]
+    fn negation_applies_to_the_next_term() {
+        assert_eq!(total("-2*3"), -6);
+        assert_eq!(total("--4"), 4);
+        assert_eq!(total("5--2"), 7);
+    }
+
+    #[test]
+    fn dice_are_terms() {
+        assert_eq!(total("3d1+2"), 5);
+        assert_eq!(total("2*4d1"), 8);
+        assert_eq!(total("d1"), 1);
+        assert_eq!(total("4d1kh2"), 2);
+        assert_eq!(total("4d1dl1"), 3);
+    }
+
+    #[test]
+    fn dice_bounds() {
+        assert!(parse("1d32767").is_ok());
+        assert_eq!(parse_error("1d32768").0, 2);
+        assert_eq!(parse_error("1d0").0, 2);
+        assert_eq!(parse_error("0d6").0, 0);
+        assert!(matches!(parse("65535d6"), Err(DiceError::TooManyDice)));
+        assert!(matches!(parse("40000d6+40000d6"), Err(DiceError::TooManyDice)));
+        assert_eq!(parse_error("2d6kh3").0, 5);
+    }
+
+    #[test]
+    fn reports_where_an_expression_went_wrong() {
+        assert_eq!(parse_error("").1, "the expression is empty");
+        assert_eq!(parse_error("2+").1, "unexpected end of expression");
+        assert_eq!(parse_error("(2+3").0, 0);
+        assert_eq!(parse_error("2+3)").0, 3);
+        assert_eq!(parse_error("1d6!!").0, 4);
+        assert_eq!(parse_error("1d1!").0, 3);
+        assert_eq!(parse_error("4d6kh1kl1").0, 6);
+        assert_eq!(parse_error("99999999999999999999").1, "number is too large");
+    }
+
+    #[test]
+    fn pools() {
+        let pool = parse_pool("6").unwrap();
+        assert_eq!((pool.count, pool.sides, pool.target, pool.explode), (6, DEFAULT_POOL_SIDES, DEFAULT_POOL_TARGET, false));
+
+        let pool = parse_pool("4d10!>7+2").unwrap();
+        assert_eq!((pool.count, pool.sides, pool.target, pool.explode), (6, 10, 8, true));
+
+        // the default target is brought down to fit smaller dice
+        assert_eq!(parse_pool("3d4").unwrap().target, 4);
+
+        for (expr, pos) in [("3d6>=7", 5), ("3d6>6", 4), ("0", 0), ("3d6>=5+", 7), ("3d1!", 3)] {
+            match parse_pool(expr) {
+                Err(DiceError::Parse { pos: found, .. }) => assert_eq!(found, pos, "{}", expr),
+                _ => panic!("{} did not fail to parse", expr),
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use rand::Rng;
+
+use std::fmt::Write;
+
+use super::{expr::RollState, DiceError};
+
+/// Pools are made of d6s unless told otherwise
+pub(crate) const DEFAULT_POOL_SIDES: i16 = 6;
+
+/// The lowest roll on a die which counts as a success unless told otherwise
+pub(crate) const DEFAULT_POOL_TARGET: i16 = 5;
+
+pub(crate) struct DicePool {
+    pub count: u16,
+    pub sides: i16,
+    pub target: i16,
+    pub explode: bool,
+}
+
+pub struct PoolResult {
+    pub successes: usize,
+    pub target: i16,
+    pub sides: i16,
+    pub breakdown: String,
+    pub rolls: Vec<i16>,
+}
+
+impl DicePool {
+    /// Rolls every die in the pool, any die which explodes adds another die to the pool
+    pub fn roll<R: Rng>(&self, state: &mut RollState<R>) -> Result<PoolResult, DiceError> {
+        let mut successes = 0;
+        let mut remaining = self.count as usize;
+        let mut breakdown = String::from("[");
+
+        while remaining > 0 {
+            remaining -= 1;
+            let val = state.roll_one(self.sides)?;
+            if self.explode && val == self.sides {
+                remaining += 1;
+            }
+
+            if breakdown.len() > 1 {
+                breakdown.push_str(", ");
+            }
+
+            if val >= self.target {
+                successes += 1;
+                let _ = write!(breakdown, "**{}**", val);
+            } else {
+                let _ = write!(breakdown, "{}", val);
+            }
+        }
+        breakdown.push(']');
+
+        Ok(PoolResult {
+            successes,
+            target: self.target,
+            sides: self.sides,
+            breakdown,
+            rolls: std::mem::take(&mut state.rolls),
+        })
+    }
+}
+
+/// Written back out in the form the pool parser reads
+impl std::fmt::Display for DicePool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        if self.explode {
+            write!(f, "!")?;
+        }
+        write!(f, ">={}", self.target)
+    }
+}
+
+impl std::fmt::Display for PoolResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = if self.successes == 1 { "" } else { "es" };
+        writeln!(
+            f,
+            "You rolled {} success{} ({}+ on a d{})",
+            self.successes, plural, self.target, self.sides
+        )?;
+
+        if self.breakdown.len() > 1850 {
+            write!(f, "[There were too many die rolls to show the result of each one]")
+        } else {
+            write!(f, "{}", self.breakdown)
+        }
+    }
+}
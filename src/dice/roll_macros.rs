@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use serenity::{
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::{channel::Message, id::UserId},
+    prelude::*,
+};
+
+use std::collections::{BTreeMap, HashMap};
+
+use simple_error::SimpleError;
+
+use super::DieRoll;
+
+const MACRO_FILE: &str = "./macros.json";
+
+/// The most macros a single user may save
+const MAX_MACROS: usize = 25;
+
+const MAX_NAME_LEN: usize = 32;
+
+/// Named roll expressions saved by each user
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct RollMacros(HashMap<u64, BTreeMap<String, String>>);
+
+impl RollMacros {
+    /// Loads the saved macros, starting fresh if there are none yet
+    pub async fn import() -> RwLock<RollMacros> {
+        let macros = match tokio::fs::read_to_string(MACRO_FILE).await {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|why| {
+                eprintln!("Could not parse {}, starting with no macros: {}", MACRO_FILE, why);
+                RollMacros::default()
+            }),
+            Err(_) => RollMacros::default(),
+        };
+        RwLock::new(macros)
+    }
+
+    async fn save(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string(&self.0)?;
+        tokio::fs::write(MACRO_FILE, json).await?;
+        Ok(())
+    }
+
+    pub fn get(&self, user: UserId, name: &str) -> Option<&str> {
+        self.0
+            .get(&user.0)?
+            .get(&name.to_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn list(&self, user: UserId) -> Option<&BTreeMap<String, String>> {
+        self.0.get(&user.0).filter(|macros| !macros.is_empty())
+    }
+
+    pub fn set(&mut self, user: UserId, name: &str, expr: &str) -> Result<(), SimpleError> {
+        if name.len() > MAX_NAME_LEN {
+            return Err(SimpleError::new(format!(
+                "Macro names can be at most {} characters long",
+                MAX_NAME_LEN
+            )));
+        }
+
+        let starts_with_letter = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic());
+        if !starts_with_letter || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(SimpleError::new(
+                "Macro names must start with a letter and only contain letters, numbers, - and _",
+            ));
+        }
+
+        // a macro named like a roll would make that roll impossible to make
+        if DieRoll::validate(name).is_ok() {
+            return Err(SimpleError::new("Macro names cannot themselves be a valid roll"));
+        }
+
+        DieRoll::validate(expr).map_err(|why| SimpleError::new(why.to_string()))?;
+
+        let macros = self.0.entry(user.0).or_default();
+        let name = name.to_lowercase();
+        if !macros.contains_key(&name) && macros.len() >= MAX_MACROS {
+            return Err(SimpleError::new(format!(
+                "You can only have {} macros, delete one first",
+                MAX_MACROS
+            )));
+        }
+
+        macros.insert(name, expr.trim().to_owned());
+        Ok(())
+    }
+
+    pub fn remove(&mut self, user: UserId, name: &str) -> Option<String> {
+        let macros = self.0.get_mut(&user.0)?;
+        let removed = macros.remove(&name.to_lowercase());
+        if macros.is_empty() {
+            self.0.remove(&user.0);
+        }
+        removed
+    }
+}
+
+impl TypeMapKey for RollMacros {
+    type Value = RwLock<RollMacros>;
+}
+
+/// If the user has a macro with this name returns its expression, otherwise returns what was given
+pub(crate) async fn expand_macro(ctx: &Context, user: UserId, to_roll: &str) -> String {
+    let data = ctx.data.read().await;
+    let macros_lock = data.get::<RollMacros>().expect("roll macros not found");
+    let macros = macros_lock.read().await;
+    macros
+        .get(user, to_roll.trim())
+        .unwrap_or(to_roll)
+        .to_owned()
+}
+
+#[group]
+#[prefixes("macro", "macros")]
+#[default_command(list_macros)]
+#[commands(set_macro, list_macros, delete_macro)]
+/// A group of commands for saving named rolls, which can then be rolled with the roll command
+struct Macros;
+
+#[command("set")]
+/// Save a roll under a name, replacing any previous roll with that name
+/// Afterwards the name can be given to the roll command instead of the roll
+#[example = "sword 2d6 + 3"]
+async fn set_macro(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.len() < 2 {
+        reply!(ctx, msg, "You must supply a name and a roll to save");
+        return Ok(());
+    }
+
+    let name = args.single::<String>()?;
+    let expr = args.rest();
+
+    let data = ctx.data.read().await;
+    let macros_lock = data.get::<RollMacros>().expect("roll macros not found");
+    let mut macros = macros_lock.write().await;
+
+    if let Err(why) = macros.set(msg.author.id, &name, expr) {
+        reply!(ctx, msg, why);
+        return Ok(());
+    }
+
+    if let Err(why) = macros.save().await {
+        eprintln!("Could not save macros: {:?}", why);
+        reply!(ctx, msg, "Macro set, but it could not be saved and will be lost on a restart");
+        return Ok(());
+    }
+
+    reply!(ctx, msg, format!("Saved {} as `{}`", name.to_lowercase(), expr.trim()));
+    Ok(())
+}
+
+#[command("list")]
+/// List all of your saved rolls
+async fn list_macros(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let macros_lock = data.get::<RollMacros>().expect("roll macros not found");
+    let macros = macros_lock.read().await;
+
+    match macros.list(msg.author.id) {
+        Some(list) => {
+            let to_send = list
+                .iter()
+                .map(|(name, expr)| format!("{}: `{}`", name, expr))
+                .collect::<Vec<String>>();
+            long_say!(ctx, msg, to_send, "\n");
+        }
+        None => reply!(ctx, msg, "You have no saved macros"),
+    }
+    Ok(())
+}
+
+#[command("del")]
+#[aliases("delete", "remove")]
+/// Delete one of your saved rolls
+#[example = "sword"]
+async fn delete_macro(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must supply the name of the macro to delete");
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let macros_lock = data.get::<RollMacros>().expect("roll macros not found");
+    let mut macros = macros_lock.write().await;
+
+    let name = args.rest().trim();
+    if macros.remove(msg.author.id, name).is_none() {
+        reply!(ctx, msg, format!("You have no macro named {}", name));
+        return Ok(());
+    }
+
+    if let Err(why) = macros.save().await {
+        eprintln!("Could not save macros: {:?}", why);
+    }
+
+    reply!(ctx, msg, format!("Deleted {}", name.to_lowercase()));
+    Ok(())
+}
@@ -0,0 +1,314 @@
+use rand::Rng;
+use serenity::{
+    model::{id::ChannelId, user::User},
+    prelude::*,
+};
+
+use simple_error::SimpleError;
+
+use super::{
+    history::{roll_and_record, RollKind},
+    DiceError, DieRoll,
+};
+use crate::library::battlechip::skills::Attributes;
+
+const DND_STATS: [&str; 6] = ["STR", "DEX", "CON", "INT", "WIS", "CHA"];
+
+const STANDARD_ARRAY: [i64; 6] = [15, 14, 13, 12, 10, 8];
+
+const POINT_BUY_BUDGET: i64 = 27;
+
+const POINT_BUY_MIN: i64 = 8;
+
+/// The cost of each score from 8 to 15 under point buy
+const POINT_BUY_COSTS: [i64; 8] = [0, 1, 2, 3, 4, 5, 7, 9];
+
+const BNB_ATTRIBUTES: [Attributes; 3] = [Attributes::Mind, Attributes::Body, Attributes::Spirit];
+
+/// The total of a new navi's attributes
+const BNB_ATTRIBUTE_POINTS: i64 = 6;
+
+const BNB_ATTRIBUTE_MIN: i64 = 1;
+
+/// The highest any one attribute may start at
+const BNB_ATTRIBUTE_CAP: i64 = 4;
+
+/// The ways character stats can be generated
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StatMethod {
+    DropLowest,
+    StandardArray,
+    PointBuy,
+    Bnb,
+}
+
+impl std::str::FromStr for StatMethod {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<StatMethod, SimpleError> {
+        match to_parse.to_lowercase().as_str() {
+            "4d6" | "4d6dl1" | "droplowest" | "roll" => Ok(StatMethod::DropLowest),
+            "array" | "standard" | "standardarray" => Ok(StatMethod::StandardArray),
+            "pointbuy" | "point-buy" | "pb" => Ok(StatMethod::PointBuy),
+            "bnb" | "attributes" => Ok(StatMethod::Bnb),
+            _ => Err(SimpleError::new(format!(
+                "{} is not a known method, the methods are 4d6, array, pointbuy, and bnb",
+                to_parse
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for StatMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatMethod::DropLowest => write!(f, "4d6"),
+            StatMethod::StandardArray => write!(f, "array"),
+            StatMethod::PointBuy => write!(f, "pointbuy"),
+            StatMethod::Bnb => write!(f, "bnb"),
+        }
+    }
+}
+
+/// A labelled set of generated stats
+pub(crate) struct StatBlock {
+    title: String,
+    stats: Vec<(String, i64, String)>,
+    footer: Option<String>,
+    pub dice: Vec<i16>,
+}
+
+impl StatBlock {
+    /// The values on a single line, for recording the result
+    pub fn summary(&self) -> String {
+        self.stats
+            .iter()
+            .map(|(label, val, _)| format!("{} {}", label, val))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+impl std::fmt::Display for StatBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.title)?;
+        for (label, val, detail) in &self.stats {
+            write!(f, "\n**{}**: {}", label, val)?;
+            if !detail.is_empty() {
+                write!(f, " {}", detail)?;
+            }
+        }
+        if let Some(footer) = &self.footer {
+            write!(f, "\n{}", footer)?;
+        }
+        Ok(())
+    }
+}
+
+impl StatMethod {
+    /// Whether this method needs dice, and so should be recorded in the roll history
+    pub fn is_random(self, values: &[i64]) -> bool {
+        match self {
+            StatMethod::DropLowest => true,
+            StatMethod::Bnb => values.is_empty(),
+            StatMethod::StandardArray | StatMethod::PointBuy => false,
+        }
+    }
+
+    /// Generates stats with one of the methods which needs dice
+    pub fn roll<R: Rng>(self, rng: &mut R) -> Result<StatBlock, DiceError> {
+        match self {
+            StatMethod::Bnb => Ok(roll_bnb(rng)),
+            _ => roll_drop_lowest(rng),
+        }
+    }
+
+    /// Generates stats with one of the methods which needs no dice, checking any values given
+    pub fn build(self, values: &[i64]) -> Result<StatBlock, SimpleError> {
+        match self {
+            StatMethod::StandardArray if !values.is_empty() => {
+                Err(SimpleError::new("The standard array does not take any values"))
+            }
+            StatMethod::StandardArray => Ok(standard_array()),
+            StatMethod::PointBuy => point_buy(values),
+            StatMethod::Bnb => bnb_allocation(values),
+            StatMethod::DropLowest => Err(SimpleError::new("4d6 drop lowest must be rolled")),
+        }
+    }
+}
+
+fn roll_drop_lowest<R: Rng>(rng: &mut R) -> Result<StatBlock, DiceError> {
+    let mut stats = Vec::with_capacity(DND_STATS.len());
+    let mut dice = Vec::with_capacity(DND_STATS.len() * 4);
+    for label in &DND_STATS {
+        let res = DieRoll::roll_dice_with("4d6dl1", false, rng)?;
+        stats.push((label.to_string(), res.total, res.breakdown));
+        dice.extend(res.rolls);
+    }
+
+    Ok(StatBlock {
+        title: String::from("4d6 drop the lowest"),
+        stats,
+        footer: None,
+        dice,
+    })
+}
+
+fn standard_array() -> StatBlock {
+    StatBlock {
+        title: String::from("Standard array"),
+        stats: DND_STATS
+            .iter()
+            .zip(STANDARD_ARRAY.iter())
+            .map(|(label, val)| (label.to_string(), *val, String::new()))
+            .collect(),
+        footer: Some(String::from("Swap the values around to suit your character")),
+        dice: vec![],
+    }
+}
+
+fn point_buy(values: &[i64]) -> Result<StatBlock, SimpleError> {
+    if values.len() != DND_STATS.len() {
+        return Err(SimpleError::new(format!(
+            "Point buy needs a score for each of {}",
+            DND_STATS.join(", ")
+        )));
+    }
+
+    let max = POINT_BUY_MIN + POINT_BUY_COSTS.len() as i64 - 1;
+    let mut stats = Vec::with_capacity(DND_STATS.len());
+    let mut spent = 0;
+    for (label, val) in DND_STATS.iter().zip(values) {
+        if !(POINT_BUY_MIN..=max).contains(val) {
+            return Err(SimpleError::new(format!(
+                "{} is {}, but point buy scores must be from {} to {}",
+                label, val, POINT_BUY_MIN, max
+            )));
+        }
+        let cost = POINT_BUY_COSTS[(val - POINT_BUY_MIN) as usize];
+        spent += cost;
+        stats.push((label.to_string(), *val, format!("({} points)", cost)));
+    }
+
+    if spent > POINT_BUY_BUDGET {
+        return Err(SimpleError::new(format!(
+            "Those scores cost {} points, but point buy only allows {}",
+            spent, POINT_BUY_BUDGET
+        )));
+    }
+
+    Ok(StatBlock {
+        title: String::from("Point buy"),
+        stats,
+        footer: Some(format!(
+            "{} of {} points spent, {} left",
+            spent,
+            POINT_BUY_BUDGET,
+            POINT_BUY_BUDGET - spent
+        )),
+        dice: vec![],
+    })
+}
+
+fn bnb_block(values: [i64; 3], title: &str) -> StatBlock {
+    StatBlock {
+        title: title.to_owned(),
+        stats: BNB_ATTRIBUTES
+            .iter()
+            .zip(values.iter())
+            .map(|(attr, val)| (attr.to_string(), *val, format!("({}d6)", val)))
+            .collect(),
+        footer: None,
+        dice: vec![],
+    }
+}
+
+fn bnb_allocation(values: &[i64]) -> Result<StatBlock, SimpleError> {
+    let values: [i64; 3] = match values {
+        [mind, body, spirit] => [*mind, *body, *spirit],
+        _ => {
+            return Err(SimpleError::new(
+                "A BnB allocation needs a value for each of Mind, Body, and Spirit",
+            ))
+        }
+    };
+
+    for (attr, val) in BNB_ATTRIBUTES.iter().zip(values.iter()) {
+        if !(BNB_ATTRIBUTE_MIN..=BNB_ATTRIBUTE_CAP).contains(val) {
+            return Err(SimpleError::new(format!(
+                "{} is {}, but attributes must be from {} to {}",
+                attr, val, BNB_ATTRIBUTE_MIN, BNB_ATTRIBUTE_CAP
+            )));
+        }
+    }
+
+    let total: i64 = values.iter().sum();
+    if total != BNB_ATTRIBUTE_POINTS {
+        return Err(SimpleError::new(format!(
+            "Those attributes add up to {}, but they must add up to {}",
+            total, BNB_ATTRIBUTE_POINTS
+        )));
+    }
+
+    Ok(bnb_block(values, "BnB attributes"))
+}
+
+/// Spends a new navi's attribute points one at a time on random attributes which aren't capped
+fn roll_bnb<R: Rng>(rng: &mut R) -> StatBlock {
+    let mut values = [BNB_ATTRIBUTE_MIN; 3];
+    let mut left = BNB_ATTRIBUTE_POINTS - BNB_ATTRIBUTE_MIN * values.len() as i64;
+    while left > 0 {
+        let open = (0..values.len())
+            .filter(|index| values[*index] < BNB_ATTRIBUTE_CAP)
+            .collect::<Vec<usize>>();
+        let index = open[rng.gen_range(0..open.len())];
+        values[index] += 1;
+        left -= 1;
+    }
+
+    // the allocation is recorded as the result, there are no dice to record
+    bnb_block(values, "Randomly allocated BnB attributes")
+}
+
+/// Generates stats for a user with the named method, rolling and recording them if the method needs dice
+pub(crate) async fn generate_stats(
+    ctx: &Context,
+    user: &User,
+    channel: ChannelId,
+    method: Option<&str>,
+    values: &str,
+) -> String {
+    let method = match method.map(str::parse::<StatMethod>) {
+        Some(Ok(method)) => method,
+        Some(Err(why)) => return why.to_string(),
+        None => StatMethod::DropLowest,
+    };
+
+    let mut parsed = Vec::new();
+    for val in values.split(|c: char| c.is_whitespace() || c == ',').filter(|v| !v.is_empty()) {
+        match val.parse::<i64>() {
+            Ok(val) => parsed.push(val),
+            Err(_) => return format!("{} is not a whole number", val),
+        }
+    }
+
+    if !method.is_random(&parsed) {
+        return match method.build(&parsed) {
+            Ok(block) => block.to_string(),
+            Err(why) => why.to_string(),
+        };
+    }
+
+    if !parsed.is_empty() {
+        return format!("The {} method does not take any values", method);
+    }
+
+    match roll_and_record(ctx, user, channel, RollKind::Stats, method.to_string(), false).await {
+        Ok(Ok(stats)) => stats,
+        Ok(Err(why)) => why.to_string(),
+        Err(why) => {
+            eprintln!("Error occurred trying to roll stats\n{:?}", why);
+            String::from("An error occurred while rolling stats, inform Major")
+        }
+    }
+}
@@ -0,0 +1,636 @@
+use serenity::{
+    framework::standard::{
+        macros::{command, group},
+        Args, CommandResult,
+    },
+    model::channel::Message,
+    prelude::*,
+};
+
+use std::{collections::HashMap, fmt::Write, sync::Arc};
+
+use simple_error::SimpleError;
+
+use crate::{
+    dice::DieRoll,
+    library::{
+        battlechip::skills::Skills,
+        virus_library::{Virus, VirusCheck, VirusLibrary},
+        Library,
+    },
+    util::split_last,
+};
+
+/// The most combatants a single channel may track
+const MAX_COMBATANTS: usize = 40;
+
+/// Viruses roll an Agility check for their initiative
+const VIRUS_INITIATIVE: VirusCheck = VirusCheck::Skill(Skills::Agility);
+
+struct Combatant {
+    name: String,
+    initiative: Option<i64>,
+    virus: Option<Arc<Virus>>,
+}
+
+/// The turn order of a single channel
+#[derive(Default)]
+pub struct Tracker {
+    combatants: Vec<Combatant>,
+    turn: usize,
+    round: u32,
+    /// The current combatant was removed, so whoever is at `turn` is up next rather than acting
+    pending: bool,
+}
+
+impl Tracker {
+    fn find(&self, name: &str) -> Result<usize, SimpleError> {
+        let name = name.trim().to_lowercase();
+        if let Some(index) = self
+            .combatants
+            .iter()
+            .position(|c| c.name.to_lowercase() == name)
+        {
+            return Ok(index);
+        }
+
+        let matches = self
+            .combatants
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.name.to_lowercase().starts_with(&name))
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+
+        match matches.as_slice() {
+            [index] => Ok(*index),
+            [] => Err(SimpleError::new(format!(
+                "Nobody named {} is in the initiative order",
+                name
+            ))),
+            _ => Err(SimpleError::new(format!(
+                "More than one combatant's name starts with {}",
+                name
+            ))),
+        }
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        initiative: Option<i64>,
+        virus: Option<Arc<Virus>>,
+    ) -> Result<String, SimpleError> {
+        if self.combatants.len() >= MAX_COMBATANTS {
+            return Err(SimpleError::new(format!(
+                "Cowardly refusing to track more than {} combatants",
+                MAX_COMBATANTS
+            )));
+        }
+
+        // number duplicate names so each combatant can still be told apart
+        let mut unique_name = name.trim().to_owned();
+        let mut num = 1;
+        while self
+            .combatants
+            .iter()
+            .any(|c| c.name.eq_ignore_ascii_case(&unique_name))
+        {
+            num += 1;
+            unique_name = format!("{} {}", name.trim(), num);
+        }
+
+        self.combatants.push(Combatant {
+            name: unique_name.clone(),
+            initiative,
+            virus,
+        });
+        self.sort();
+        Ok(unique_name)
+    }
+
+    fn remove(&mut self, name: &str) -> Result<String, SimpleError> {
+        let index = self.find(name)?;
+        let removed = self.combatants.remove(index);
+        if self.combatants.is_empty() {
+            self.turn = 0;
+        } else if index < self.turn {
+            self.turn -= 1;
+        } else if index == self.turn && self.round > 0 {
+            // whoever slid into the removed combatant's place goes next
+            self.pending = true;
+            if self.turn >= self.combatants.len() {
+                self.turn = 0;
+                self.round += 1;
+            }
+        }
+        Ok(removed.name)
+    }
+
+    fn set(&mut self, name: &str, initiative: i64) -> Result<String, SimpleError> {
+        let index = self.find(name)?;
+        self.combatants[index].initiative = Some(initiative);
+        let name = self.combatants[index].name.clone();
+        self.sort();
+        Ok(name)
+    }
+
+    /// Keeps the order highest initiative first, with anyone who hasn't rolled at the end,
+    /// making sure it is still the same combatant's turn afterwards
+    fn sort(&mut self) {
+        let current = self.combatants.get(self.turn).map(|c| c.name.clone());
+        self.combatants.sort_by(|a, b| {
+            b.initiative
+                .unwrap_or(i64::MIN)
+                .cmp(&a.initiative.unwrap_or(i64::MIN))
+        });
+        if self.round > 0 {
+            if let Some(name) = current {
+                self.turn = self
+                    .combatants
+                    .iter()
+                    .position(|c| c.name == name)
+                    .unwrap_or(0);
+            }
+        }
+    }
+
+    /// Finds the combatant named at the start of some arguments, and the roll after their name if there is one.
+    /// The roll is split off wherever the rest is a valid dice expression, so it may contain spaces.
+    fn find_with_roll(&self, args: &str) -> Result<(usize, Option<String>), SimpleError> {
+        let args = args.trim();
+        let whole = match self.find(args) {
+            Ok(index) => return Ok((index, None)),
+            Err(why) => why,
+        };
+
+        args.char_indices()
+            .filter(|(_, c)| c.is_whitespace())
+            .map(|(split, _)| (args[..split].trim(), args[split..].trim()))
+            .filter(|(name, to_roll)| !name.is_empty() && DieRoll::validate(to_roll).is_ok())
+            .find_map(|(name, to_roll)| self.find(name).ok().map(|index| (index, Some(to_roll.to_owned()))))
+            .ok_or(whole)
+    }
+
+    /// Moves to the next combatant's turn, starting combat if it hasn't started
+    fn next(&mut self) -> Result<&Combatant, SimpleError> {
+        if self.combatants.is_empty() {
+            return Err(SimpleError::new("There is nobody in the initiative order"));
+        }
+
+        if self.round == 0 {
+            self.round = 1;
+            self.turn = 0;
+        } else if self.pending {
+            self.pending = false;
+        } else {
+            self.turn += 1;
+            if self.turn >= self.combatants.len() {
+                self.turn = 0;
+                self.round += 1;
+            }
+        }
+        Ok(&self.combatants[self.turn])
+    }
+}
+
+impl std::fmt::Display for Tracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.round == 0 {
+            writeln!(f, "```\nCombat has not started")?;
+        } else {
+            write!(f, "```\nRound {}", self.round)?;
+            match self.combatants.get(self.turn) {
+                Some(next) if self.pending => writeln!(f, ", {} is up next", next.name)?,
+                _ => writeln!(f)?,
+            }
+        }
+
+        for (index, combatant) in self.combatants.iter().enumerate() {
+            let marker = if self.round > 0 && index == self.turn && !self.pending {
+                ">"
+            } else {
+                " "
+            };
+            let initiative = combatant
+                .initiative
+                .map_or_else(|| String::from("--"), |i| i.to_string());
+            write!(
+                f,
+                "{} {}. {} ({})",
+                marker,
+                index + 1,
+                combatant.name,
+                initiative
+            )?;
+            if let Some(virus) = &combatant.virus {
+                write!(f, " | HP: {} | AC: {}", virus.h_p, virus.a_c)?;
+            }
+            writeln!(f)?;
+        }
+
+        write!(f, "```")
+    }
+}
+
+/// Every channel's initiative order, kept in memory only
+pub struct InitiativeTrackers;
+
+impl TypeMapKey for InitiativeTrackers {
+    type Value = RwLock<HashMap<u64, Tracker>>;
+}
+
+#[group]
+#[prefixes("init", "initiative")]
+#[default_command(show_initiative)]
+#[commands(
+    show_initiative,
+    add_combatant,
+    add_virus_combatant,
+    roll_initiative,
+    set_initiative,
+    next_turn,
+    remove_combatant,
+    clear_initiative
+)]
+/// A group of commands for tracking the turn order of combat in a channel
+struct Initiative;
+
+#[command("show")]
+/// Show the initiative order for this channel
+async fn show_initiative(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let trackers = initiative_lock.read().await;
+
+    match trackers.get(&msg.channel_id.0) {
+        Some(tracker) if !tracker.combatants.is_empty() => reply!(ctx, msg, tracker),
+        _ => reply!(
+            ctx,
+            msg,
+            "There is nobody in the initiative order for this channel"
+        ),
+    }
+    Ok(())
+}
+
+#[command("add")]
+/// Add a player to the initiative order, optionally with their initiative
+#[example = "Lan"]
+#[example = "Lan 14"]
+async fn add_combatant(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must provide a name");
+        return Ok(());
+    }
+
+    let (name, initiative) = split_last::<i64>(args.rest());
+    let data = ctx.data.read().await;
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let mut trackers = initiative_lock.write().await;
+    let tracker = trackers.entry(msg.channel_id.0).or_default();
+
+    match tracker.add(name, initiative, None) {
+        Ok(name) => reply!(ctx, msg, format!("Added {}\n{}", name, tracker)),
+        Err(why) => reply!(ctx, msg, why),
+    }
+    Ok(())
+}
+
+#[command("virus")]
+/// Add one or more copies of a virus from the virus library
+/// Their initiative is rolled as an Agility check
+#[example = "Mettaur"]
+#[example = "Mettaur 3"]
+async fn add_virus_combatant(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must provide a virus name");
+        return Ok(());
+    }
+
+    let (name, count) = split_last::<usize>(args.rest());
+    let count = count.unwrap_or(1);
+
+    let data = ctx.data.read().await;
+    let virus_lock = data.get::<VirusLibrary>().expect("Virus library not found");
+    let virus_library = virus_lock.read().await;
+
+    let virus = match virus_library.search_lib_obj(name) {
+        Ok(virus) => Arc::clone(virus),
+        Err(viruses) => {
+            let to_say = viruses
+                .iter()
+                .map(|v| v.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ");
+            reply!(ctx, msg, format!("Did you mean: {}", to_say));
+            return Ok(());
+        }
+    };
+
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let mut trackers = initiative_lock.write().await;
+    let tracker = trackers.entry(msg.channel_id.0).or_default();
+
+    let mut reply = String::new();
+    for _ in 0..count {
+        let roll = DieRoll::roll_dice(&virus.check_expr(VIRUS_INITIATIVE), false)?;
+        match tracker.add(&virus.name, Some(roll.total), Some(Arc::clone(&virus))) {
+            Ok(name) => {
+                let _ = writeln!(
+                    reply,
+                    "Added {} with initiative {} {}",
+                    name, roll.total, roll.breakdown
+                );
+            }
+            Err(why) => {
+                let _ = writeln!(reply, "{}", why);
+                break;
+            }
+        }
+    }
+
+    reply.push_str(&tracker.to_string());
+    reply!(ctx, msg, reply);
+    Ok(())
+}
+
+#[command("roll")]
+/// Roll initiative for a combatant, viruses roll an Agility check if no roll is given
+#[example = "Lan 3d6+2"]
+#[example = "Mega Man 1d20 + 3"]
+#[example = "Mettaur"]
+async fn roll_initiative(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must provide a name");
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let mut trackers = initiative_lock.write().await;
+    let tracker = match trackers.get_mut(&msg.channel_id.0) {
+        Some(tracker) => tracker,
+        None => {
+            reply!(
+                ctx,
+                msg,
+                "There is nobody in the initiative order for this channel"
+            );
+            return Ok(());
+        }
+    };
+
+    let (index, to_roll) = match tracker.find_with_roll(args.rest()) {
+        Ok(found) => found,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    let to_roll = match (to_roll, &tracker.combatants[index].virus) {
+        (Some(to_roll), _) => to_roll,
+        (None, Some(virus)) => virus.check_expr(VIRUS_INITIATIVE),
+        (None, None) => {
+            reply!(ctx, msg, "Players must give a roll for their initiative");
+            return Ok(());
+        }
+    };
+
+    let roll = match DieRoll::roll_dice(&to_roll, false) {
+        Ok(roll) => roll,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    let name = tracker.combatants[index].name.clone();
+    tracker.set(&name, roll.total)?;
+    reply!(
+        ctx,
+        msg,
+        format!(
+            "{} rolled {} for initiative {}\n{}",
+            name, roll.total, roll.breakdown, tracker
+        )
+    );
+    Ok(())
+}
+
+#[command("set")]
+/// Set a combatant's initiative to a specific value
+#[example = "Lan 17"]
+async fn set_initiative(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (name, initiative) = match split_last::<i64>(args.rest()) {
+        (name, Some(initiative)) if !name.is_empty() => (name, initiative),
+        _ => {
+            reply!(ctx, msg, "You must provide a name and an initiative");
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let mut trackers = initiative_lock.write().await;
+
+    let res = match trackers.get_mut(&msg.channel_id.0) {
+        Some(tracker) => tracker
+            .set(name, initiative)
+            .map(|name| format!("Set {} to {}\n{}", name, initiative, tracker)),
+        None => Err(SimpleError::new(
+            "There is nobody in the initiative order for this channel",
+        )),
+    };
+
+    match res {
+        Ok(reply) => reply!(ctx, msg, reply),
+        Err(why) => reply!(ctx, msg, why),
+    }
+    Ok(())
+}
+
+#[command("next")]
+/// Advance to the next combatant's turn, starting combat if it hasn't already started
+async fn next_turn(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let mut trackers = initiative_lock.write().await;
+
+    let res = match trackers.get_mut(&msg.channel_id.0) {
+        Some(tracker) => tracker
+            .next()
+            .map(|combatant| format!("It is now {}'s turn", combatant.name))
+            .map(|turn| format!("{}\n{}", turn, tracker)),
+        None => Err(SimpleError::new(
+            "There is nobody in the initiative order for this channel",
+        )),
+    };
+
+    match res {
+        Ok(reply) => reply!(ctx, msg, reply),
+        Err(why) => reply!(ctx, msg, why),
+    }
+    Ok(())
+}
+
+#[command("remove")]
+#[aliases("defeat", "kill")]
+/// Remove a combatant from the initiative order
+#[example = "Mettaur 2"]
+async fn remove_combatant(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must provide a name");
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let mut trackers = initiative_lock.write().await;
+
+    let res = match trackers.get_mut(&msg.channel_id.0) {
+        Some(tracker) => tracker
+            .remove(args.rest())
+            .map(|name| format!("Removed {}\n{}", name, tracker)),
+        None => Err(SimpleError::new(
+            "There is nobody in the initiative order for this channel",
+        )),
+    };
+
+    match res {
+        Ok(reply) => reply!(ctx, msg, reply),
+        Err(why) => reply!(ctx, msg, why),
+    }
+    Ok(())
+}
+
+#[command("clear")]
+#[aliases("end")]
+/// End combat, removing everyone from this channel's initiative order
+async fn clear_initiative(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let initiative_lock = data
+        .get::<InitiativeTrackers>()
+        .expect("initiative not found");
+    let mut trackers = initiative_lock.write().await;
+
+    if trackers.remove(&msg.channel_id.0).is_some() {
+        reply!(
+            ctx,
+            msg,
+            "Combat is over, the initiative order has been cleared"
+        );
+    } else {
+        reply!(
+            ctx,
+            msg,
+            "There is nobody in the initiative order for this channel"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tracker in the first round with four combatants, highest initiative first
+    fn tracker() -> Tracker {
+        let mut tracker = Tracker::default();
+        for (name, initiative) in [("Lan", 18), ("Mega Man", 15), ("Mayl", 12), ("Roll", 9)] {
+            tracker.add(name, Some(initiative), None).unwrap();
+        }
+        tracker.next().unwrap();
+        tracker
+    }
+
+    fn current(tracker: &Tracker) -> &str {
+        &tracker.combatants[tracker.turn].name
+    }
+
+    #[test]
+    fn removing_the_first_combatant_on_their_turn() {
+        let mut tracker = tracker();
+        tracker.remove("Lan").unwrap();
+        assert_eq!(tracker.round, 1);
+        assert!(tracker.to_string().contains("Round 1, Mega Man is up next"));
+        assert!(!tracker.to_string().contains('>'));
+
+        // sorting keeps whoever is up next
+        tracker.add("Chaud", Some(20), None).unwrap();
+        assert_eq!(current(&tracker), "Mega Man");
+
+        assert_eq!(tracker.next().unwrap().name, "Mega Man");
+        assert_eq!(tracker.round, 1);
+        assert!(tracker.to_string().contains("> 2. Mega Man"));
+        assert_eq!(tracker.next().unwrap().name, "Mayl");
+    }
+
+    #[test]
+    fn removing_a_later_combatant_on_their_turn() {
+        let mut tracker = tracker();
+        tracker.next().unwrap();
+        tracker.remove("Mega Man").unwrap();
+        assert!(tracker.to_string().contains("Mayl is up next"));
+        assert!(!tracker.to_string().contains("> 1. Lan"));
+        assert_eq!(tracker.next().unwrap().name, "Mayl");
+        assert_eq!(tracker.next().unwrap().name, "Roll");
+        assert_eq!(tracker.next().unwrap().name, "Lan");
+        assert_eq!(tracker.round, 2);
+    }
+
+    #[test]
+    fn removing_the_last_combatant_on_their_turn() {
+        let mut tracker = tracker();
+        for _ in 0..3 {
+            tracker.next().unwrap();
+        }
+        tracker.remove("Roll").unwrap();
+        assert_eq!(tracker.next().unwrap().name, "Lan");
+        assert_eq!(tracker.round, 2);
+        assert_eq!(tracker.next().unwrap().name, "Mega Man");
+    }
+
+    #[test]
+    fn removing_someone_else_keeps_the_turn() {
+        let mut tracker = tracker();
+        tracker.next().unwrap();
+        tracker.remove("Lan").unwrap();
+        assert_eq!(current(&tracker), "Mega Man");
+        assert_eq!(tracker.next().unwrap().name, "Mayl");
+    }
+
+    #[test]
+    fn splits_a_spaced_roll_from_a_spaced_name() {
+        let tracker = tracker();
+        let (index, to_roll) = tracker.find_with_roll("Mega Man 1d20 + 3").unwrap();
+        assert_eq!(tracker.combatants[index].name, "Mega Man");
+        assert_eq!(to_roll.as_deref(), Some("1d20 + 3"));
+
+        let (index, to_roll) = tracker.find_with_roll("Mega Man").unwrap();
+        assert_eq!(tracker.combatants[index].name, "Mega Man");
+        assert_eq!(to_roll, None);
+
+        let (index, to_roll) = tracker.find_with_roll("Lan 3d6+2").unwrap();
+        assert_eq!(tracker.combatants[index].name, "Lan");
+        assert_eq!(to_roll.as_deref(), Some("3d6+2"));
+
+        assert!(tracker.find_with_roll("Bass 1d20").is_err());
+        assert!(tracker.find_with_roll("Mega Man fast").is_err());
+    }
+}
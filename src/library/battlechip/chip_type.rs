@@ -1,7 +1,7 @@
 use serde::Serialize;
 use simple_error::SimpleError;
 
-#[derive(Serialize, PartialEq, Eq)]
+#[derive(Serialize, PartialEq, Eq, Clone, Copy)]
 pub enum ChipClass {
     Standard,
     Mega,
@@ -43,7 +43,7 @@ impl std::default::Default for ChipClass {
     }
 }
 
-#[derive(Serialize, PartialEq, Eq)]
+#[derive(Serialize, PartialEq, Eq, Clone, Copy)]
 pub enum ChipType {
     Burst,
     Construct,
@@ -57,7 +57,7 @@ pub enum ChipType {
 }
 
 impl ChipType {
-    pub fn to_std_chip_class(&self) -> ChipClass {
+    pub fn to_std_chip_class(self) -> ChipClass {
         match self {
             ChipType::Trap |
             ChipType::Support |
@@ -0,0 +1,121 @@
+use serde::Serialize;
+use simple_error::SimpleError;
+
+/// The damage roll of a chip, such as 2d6 or 1d8+2
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all(serialize = "PascalCase"))]
+pub struct ChipDamage {
+    pub dice: u16,
+    pub sides: u16,
+    pub modifier: i16,
+}
+
+impl ChipDamage {
+    pub fn average(&self) -> f64 {
+        self.dice as f64 * (self.sides as f64 + 1.0) / 2.0 + self.modifier as f64
+    }
+
+    pub fn max(&self) -> i64 {
+        self.dice as i64 * self.sides as i64 + self.modifier as i64
+    }
+}
+
+impl std::str::FromStr for ChipDamage {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<ChipDamage, SimpleError> {
+        let to_parse = to_parse.replace(' ', "").to_ascii_lowercase();
+        let (dice, rest) = to_parse
+            .split_once('d')
+            .ok_or_else(|| SimpleError::new("Failed to parse damage"))?;
+
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(index) => (&rest[..index], rest[index..].trim_start_matches('+')),
+            None => (rest, "0"),
+        };
+
+        Ok(ChipDamage {
+            dice: dice.parse().map_err(|_| SimpleError::new("Failed to parse damage dice"))?,
+            sides: sides.parse().map_err(|_| SimpleError::new("Failed to parse damage sides"))?,
+            modifier: modifier
+                .parse()
+                .map_err(|_| SimpleError::new("Failed to parse damage modifier"))?,
+        })
+    }
+}
+
+impl std::fmt::Display for ChipDamage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.dice, self.sides)?;
+        match self.modifier {
+            0 => Ok(()),
+            modifier if modifier > 0 => write!(f, "+{}", modifier),
+            modifier => write!(f, "{}", modifier),
+        }
+    }
+}
+
+/// The least and most times a chip can hit
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all(serialize = "PascalCase"))]
+pub struct ChipHits {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl ChipHits {
+    pub fn contains(&self, hits: u16) -> bool {
+        (self.min..=self.max).contains(&hits)
+    }
+}
+
+impl std::str::FromStr for ChipHits {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<ChipHits, SimpleError> {
+        let mut bounds = to_parse.splitn(2, '-');
+        let min = bounds
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .parse::<u16>()
+            .map_err(|_| SimpleError::new("Failed to parse hits"))?;
+        let max = match bounds.next() {
+            Some(max) => max
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| SimpleError::new("Failed to parse hits"))?,
+            None => min,
+        };
+
+        if max < min {
+            return Err(SimpleError::new("The fewest hits cannot be more than the most hits"));
+        }
+        Ok(ChipHits { min, max })
+    }
+}
+
+impl std::fmt::Display for ChipHits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}-{}", self.min, self.max)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hits_past_a_byte() {
+        let hits = "300".parse::<ChipHits>().unwrap();
+        assert_eq!((hits.min, hits.max), (300, 300));
+        let hits = "2-1000".parse::<ChipHits>().unwrap();
+        assert!(hits.contains(256));
+        assert!("3-2".parse::<ChipHits>().is_err());
+        assert!("many".parse::<ChipHits>().is_err());
+    }
+}
@@ -1,5 +1,11 @@
 use crate::library::{
-    battlechip::{chip_type::{ChipClass, ChipType}, ranges::Ranges, skills::Skills},
+    battlechip::{
+        chip_type::{ChipClass, ChipType},
+        damage::{ChipDamage, ChipHits},
+        ranges::Ranges,
+        saves::ChipSave,
+        skills::Skills,
+    },
     elements::Elements,
 };
 
@@ -15,13 +21,17 @@ use std::{
 
 use unicode_normalization::UnicodeNormalization;
 
-use crate::library::LibraryObject;
+use crate::library::{render::embed_value, LibraryObject};
+use serenity::builder::CreateEmbed;
 use simple_error::SimpleError;
 
 use once_cell::sync::Lazy;
 
-mod chip_type;
-mod ranges;
+pub(crate) mod chip_type;
+pub(crate) mod damage;
+pub(crate) mod query;
+pub(crate) mod ranges;
+pub(crate) mod saves;
 pub(crate) mod skills;
 
 #[derive(Serialize)]
@@ -31,17 +41,26 @@ pub struct BattleChip {
     pub element: Vec<Elements>,
     pub skills: Vec<Skills>,
     pub range: Ranges,
-    pub damage: String,
+    /// The damage exactly as written in the chip list, "--" for none
+    #[serde(rename = "Damage")]
+    pub damage_text: String,
     pub class: ChipClass,
     #[serde(rename = "Type")]
     pub kind: ChipType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub blight: Option<Elements>,
-    pub hits: String,
+    /// The hits exactly as written in the chip list, "--" for none
+    #[serde(rename = "Hits")]
+    pub hits_text: String,
     pub description: String,
     pub all: String,
     pub skill_target: Skills,
     pub skill_user: Skills,
+    #[serde(rename = "DamageRoll")]
+    pub damage: Option<ChipDamage>,
+    #[serde(rename = "HitRange")]
+    pub hits: Option<ChipHits>,
+    pub saves: Vec<ChipSave>,
 }
 
 impl Ord for BattleChip {
@@ -67,16 +86,15 @@ impl Eq for BattleChip {}
 impl std::fmt::Display for BattleChip {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         
-        let damage = if self.damage == "--" {
-            Cow::Borrowed("--")
-        } else {
-            Cow::Owned(format!("{} damage", self.damage))
+        let damage = match &self.damage {
+            Some(damage) => Cow::Owned(format!("{} damage", damage)),
+            None => Cow::Borrowed("--"),
         };
 
-        let hits = if self.hits == "1" {
-            Cow::Borrowed("1 hit")
-        } else {
-            Cow::Owned(format!("{} hits", self.hits))
+        let hits = match &self.hits {
+            Some(ChipHits { min: 1, max: 1 }) => Cow::Borrowed("1 hit"),
+            Some(hits) => Cow::Owned(format!("{} hits", hits)),
+            None => Cow::Borrowed("-- hits"),
         };
 
         let skills = self.skills.iter().format_with(", ", |s, f| f(&format_args!("{}", s.abbreviation())));
@@ -103,14 +121,124 @@ impl LibraryObject for BattleChip {
     fn get_kind(&self) -> &str {
         "Chip"
     }
+
+    fn embed(&self) -> CreateEmbed {
+        let damage = match &self.damage {
+            Some(damage) => damage.to_string(),
+            None => String::from("--"),
+        };
+        let hits = match &self.hits {
+            Some(hits) => hits.to_string(),
+            None => String::from("--"),
+        };
+
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(&self.name)
+            .description(embed_value(&self.description))
+            .field("Element", embed_value(self.element.iter().format(", ")), true)
+            .field("Skills", embed_value(self.skills.iter().map(|s| s.abbreviation()).format(", ")), true)
+            .field("Range", self.range, true)
+            .field("Damage", damage, true)
+            .field("Hits", hits, true)
+            .field("Class", self.class, true)
+            .field("Type", self.kind, true);
+        if let Some(blight) = self.blight {
+            embed.field("Blight", blight, true);
+        }
+        if let Some(element) = self.element.first() {
+            embed.colour(element.colour());
+        }
+        embed
+    }
 }
 
 
-static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*?(.+?)\s-\s(.+?)\s\|\s(.+?)\s\|\s(.+?)\s\|\s(\d+d\d+|--)\s?(?:damage)?\s?\|?\s?(Mega|Giga|Dark)?\s\|\s(\d+|\d+-\d+|--)\s?(?:hits?)\s\|\s(.+?)$").expect("could not compile chip regex"));
-static R_SAVE : Lazy<Regex> = Lazy::new(|| Regex::new(r"an?\s(\w+)\scheck\sof\s\[DC\s\d+\s\+\s(\w+)]").expect("could not compile save regex"));
+/// Each capture group of the chip regex, named by the field it captures, in order
+const RE_GROUPS: [(&str, &str); 8] = [
+    ("name", r"(.+?)\s-\s"),
+    ("element", r"(.+?)\s\|\s"),
+    ("skills", r"(.+?)\s\|\s"),
+    ("range", r"(.+?)\s\|\s"),
+    ("damage", r"(\d+d\d+(?:\s?[+-]\s?\d+)?|--)\s?(?:damage)?\s?\|?\s?"),
+    ("class", r"(Mega|Giga|Dark)?\s\|\s"),
+    ("hits", r"(\d+|\d+-\d+|--)\s?(?:hits?)\s\|\s"),
+    ("type", r"(.+?)$"),
+];
+
+const RE_START: &str = r"^\s*?";
+
+static RE: Lazy<Regex> = Lazy::new(|| {
+    let pattern = RE_GROUPS.iter().fold(String::from(RE_START), |acc, (_, group)| acc + group);
+    Regex::new(&pattern).expect("could not compile chip regex")
+});
+
+/// The chip regex cut off after each group, used to find which group a line fails at
+static RE_PREFIXES: Lazy<Vec<Regex>> = Lazy::new(|| {
+    let mut pattern = String::from(RE_START);
+    RE_GROUPS
+        .iter()
+        .map(|(_, group)| {
+            pattern.push_str(group);
+            Regex::new(&pattern).expect("could not compile chip regex prefix")
+        })
+        .collect()
+});
 static R_BLIGHT : Lazy<Regex> = Lazy::new(|| Regex::new(r"[bB]light\s\((\w+)\)").expect("could not compile blight regex"));
 
+/// Why a chip could not be parsed, and which field of the chip regex it failed at
+pub struct ChipParseError {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl ChipParseError {
+    fn at(field: &'static str) -> impl Fn(SimpleError) -> ChipParseError {
+        move |why| ChipParseError { field, reason: why.as_str().to_owned() }
+    }
+}
+
+impl std::fmt::Display for ChipParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} group: {}", self.field, self.reason)
+    }
+}
+
 impl BattleChip {
+    /// Whether any save this chip forces adds the user's skill to its DC
+    pub fn has_save_user(&self, skill: Skills) -> bool {
+        (skill == Skills::None && self.saves.is_empty()) || self.saves.iter().any(|save| save.user == skill)
+    }
+
+    /// Whether any save this chip forces is rolled with the target's skill
+    pub fn has_save_target(&self, skill: Skills) -> bool {
+        (skill == Skills::None && self.saves.is_empty()) || self.saves.iter().any(|save| save.target == skill)
+    }
+
+    /// Whether a line looks like the first line of a chip
+    pub fn is_chip_header(line: &str) -> bool {
+        RE.is_match(line)
+    }
+
+    /// Finds the first group of the chip regex which a line does not match
+    fn failed_group(line: &str) -> &'static str {
+        RE_PREFIXES
+            .iter()
+            .zip(RE_GROUPS.iter())
+            .find(|(prefix, _)| !prefix.is_match(line))
+            .map_or("type", |(_, (field, _))| *field)
+    }
+
+    /// The average damage of this chip if every hit it can make lands
+    pub fn average_damage(&self) -> Option<f64> {
+        Some(self.damage?.average() * self.hits?.max as f64)
+    }
+
+    /// The most damage this chip can deal if every hit it can make lands
+    pub fn max_damage(&self) -> Option<i64> {
+        Some(self.damage?.max() * self.hits?.max as i64)
+    }
+
     fn parse_elements(elem_str: &str) -> Result<Vec<Elements>, SimpleError> {
         let mut to_ret = vec![];
         for elem in elem_str.split(", ") {
@@ -132,82 +260,80 @@ impl BattleChip {
     pub fn from_chip_string(
         first_line: &str,
         second_line: &str,
-    ) -> Result<BattleChip, SimpleError> {
-        
-        
-
-        let chip_val: Captures = RE
-            .captures(first_line)
-            .ok_or_else(|| SimpleError::new("Failed at capture stage"))?;
+    ) -> Result<BattleChip, ChipParseError> {
+        let chip_val: Captures = RE.captures(first_line).ok_or_else(|| ChipParseError {
+            field: BattleChip::failed_group(first_line),
+            reason: String::from("line does not match"),
+        })?;
 
         let chip_name = chip_val
             .get(1)
-            .ok_or_else(|| SimpleError::new("Could not get name"))?
+            .ok_or_else(|| SimpleError::new("Could not get name"))
+            .map_err(ChipParseError::at("name"))?
             .as_str()
             .trim();
-        let chip_range =
-            chip_val
-                .get(4)
-                .ok_or_else(|| SimpleError::new("Failed to convert range"))?
-                .as_str().parse::<Ranges>()?;
-        let chip_damage = chip_val
+        let chip_range = chip_val
+            .get(4)
+            .ok_or_else(|| SimpleError::new("Failed to convert range"))
+            .and_then(|range| range.as_str().parse::<Ranges>())
+            .map_err(ChipParseError::at("range"))?;
+        let damage_text = chip_val
             .get(5)
-            .ok_or_else(|| SimpleError::new("failed to get damage"))?
+            .ok_or_else(|| SimpleError::new("failed to get damage"))
+            .map_err(ChipParseError::at("damage"))?
             .as_str();
-        let chip_hits = chip_val
+        let chip_damage = match damage_text {
+            "--" => None,
+            damage => Some(damage.parse::<ChipDamage>().map_err(ChipParseError::at("damage"))?),
+        };
+        let hits_text = chip_val
             .get(7)
-            .ok_or_else(|| SimpleError::new("failed to get hits"))?
+            .ok_or_else(|| SimpleError::new("failed to get hits"))
+            .map_err(ChipParseError::at("hits"))?
             .as_str();
+        let chip_hits = match hits_text {
+            "--" => None,
+            hits => Some(hits.parse::<ChipHits>().map_err(ChipParseError::at("hits"))?),
+        };
 
-        let chip_kind = chip_val.get(8).ok_or_else(|| SimpleError::new("Failed to get chip type"))?.as_str().parse::<ChipType>()?;
+        let chip_kind = chip_val
+            .get(8)
+            .ok_or_else(|| SimpleError::new("Failed to get chip type"))
+            .and_then(|kind| kind.as_str().parse::<ChipType>())
+            .map_err(ChipParseError::at("type"))?;
 
         let chip_class = match chip_val.get(6) {
             Some(chip_class_str) => {
-                chip_class_str.as_str().parse::<ChipClass>()?
+                chip_class_str.as_str().parse::<ChipClass>().map_err(ChipParseError::at("class"))?
             }
             None => {
                 chip_kind.to_std_chip_class()
             }
         };
 
-        let parsed_elements = BattleChip::parse_elements(
-            chip_val
-                .get(2)
-                .ok_or_else(|| SimpleError::new("failed to parse element"))?
-                .as_str(),
-        )?;
-        // let skills : Vec<&str> = chip_val.get(3).unwrap().as_str().split(", ").collect();
-        let parsed_skills = BattleChip::parse_skills(
-            chip_val
-                .get(3)
-                .ok_or_else(|| SimpleError::new("failed to parse skills"))?
-                .as_str(),
-        )?;
-
-        let skill_user: Skills;
-        let skill_target: Skills;
-        // let skill_res = R_SAVE.captures(second_line);
-        if let Some(skill_res) = R_SAVE.captures(second_line) {
-            let skill_user_res = skill_res
-                .get(2)
-                .ok_or_else(|| SimpleError::new("failed to get skill user"))?
-                .as_str();
-            let skill_target_res = skill_res
-                .get(1)
-                .ok_or_else(|| SimpleError::new("failed to get skill target"))?
-                .as_str();
-            skill_user = Skills::from_str(skill_user_res).unwrap_or_default();
-            skill_target = Skills::from_str(skill_target_res).unwrap_or_default();
-        } else {
-            skill_user = Skills::None;
-            skill_target = Skills::None;
-        }
+        let parsed_elements = chip_val
+            .get(2)
+            .ok_or_else(|| SimpleError::new("failed to parse element"))
+            .and_then(|elements| BattleChip::parse_elements(elements.as_str()))
+            .map_err(ChipParseError::at("element"))?;
+        let parsed_skills = chip_val
+            .get(3)
+            .ok_or_else(|| SimpleError::new("failed to parse skills"))
+            .and_then(|skills| BattleChip::parse_skills(skills.as_str()))
+            .map_err(ChipParseError::at("skills"))?;
+
+        // the first save is kept on its own for the skill user and target searches
+        let saves = ChipSave::parse_all(second_line);
+        let (skill_user, skill_target) = saves
+            .first()
+            .map_or((Skills::None, Skills::None), |save| (save.user, save.target));
 
         let blight: Option<Elements> =
         if let Some(blight_res) = R_BLIGHT.captures(second_line) {
             let blight_elem_str = blight_res
                 .get(1)
-                .ok_or_else(|| SimpleError::new("failed to get blight"))?
+                .ok_or_else(|| SimpleError::new("failed to get blight"))
+                .map_err(ChipParseError::at("description"))?
                 .as_str();
             Some(Elements::from_str(blight_elem_str).unwrap_or(Elements::Null))
         } else {
@@ -221,15 +347,18 @@ impl BattleChip {
             element: parsed_elements,
             skills: parsed_skills,
             range: chip_range,
-            damage: chip_damage.nfc().collect::<String>(),
+            damage_text: damage_text.nfc().collect::<String>(),
             class: chip_class,
             kind: chip_kind,
             blight,
-            hits: chip_hits.nfc().collect::<String>(),
+            hits_text: hits_text.nfc().collect::<String>(),
             description: second_line.nfc().collect::<String>(),
             all: chip_all.nfc().collect::<String>(),
             skill_target,
             skill_user,
+            damage: chip_damage,
+            hits: chip_hits,
+            saves,
         };
 
         Ok(to_ret)
@@ -0,0 +1,373 @@
+use simple_error::SimpleError;
+
+use std::str::FromStr;
+
+use crate::library::{
+    battlechip::{
+        chip_type::{ChipClass, ChipType},
+        damage::ChipDamage,
+        ranges::Ranges,
+        skills::Skills,
+        BattleChip,
+    },
+    elements::Elements,
+};
+
+const FIELDS: &str = "element, blight, range, class, type, skill, user, target, check, damage, hits, name";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Comparison {
+    Equal,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    fn compare<T: PartialOrd>(self, lhs: T, rhs: T) -> bool {
+        match self {
+            Comparison::Equal => lhs == rhs,
+            Comparison::Less => lhs < rhs,
+            Comparison::LessEqual => lhs <= rhs,
+            Comparison::Greater => lhs > rhs,
+            Comparison::GreaterEqual => lhs >= rhs,
+        }
+    }
+}
+
+/// A single test against one field of a chip
+pub(crate) enum Condition {
+    Element(Elements),
+    Blight(Elements),
+    Range(Ranges),
+    Class(ChipClass),
+    Kind(ChipType),
+    Skill(Skills),
+    SkillUser(Skills),
+    SkillTarget(Skills),
+    SkillCheck(Skills),
+    NoDamage,
+    Damage(Comparison, f64),
+    NoHits,
+    Hits(Comparison, u16),
+    Name(String),
+}
+
+impl Condition {
+    fn parse(token: &str) -> Result<Condition, SimpleError> {
+        // the first operator character splits the field from its value
+        let op_start = token
+            .find([':', '=', '<', '>'])
+            .ok_or_else(|| {
+                SimpleError::new(format!(
+                    "{} is not a condition, conditions look like field:value, e.g. element:fire",
+                    token
+                ))
+            })?;
+        let field = token[..op_start].to_ascii_lowercase();
+        let rest = &token[op_start..];
+
+        let (op, value) = if let Some(value) = rest.strip_prefix(">=") {
+            (Comparison::GreaterEqual, value)
+        } else if let Some(value) = rest.strip_prefix("<=") {
+            (Comparison::LessEqual, value)
+        } else if let Some(value) = rest.strip_prefix('>') {
+            (Comparison::Greater, value)
+        } else if let Some(value) = rest.strip_prefix('<') {
+            (Comparison::Less, value)
+        } else {
+            (Comparison::Equal, &rest[1..])
+        };
+
+        let value = value.trim_matches('"');
+        if value.is_empty() {
+            return Err(SimpleError::new(format!("{} is missing a value", token)));
+        }
+
+        if op != Comparison::Equal && field != "damage" && field != "dmg" && field != "hits" {
+            return Err(SimpleError::new(format!(
+                "Only damage and hits can be compared with <, <=, >, or >=, not {}",
+                field
+            )));
+        }
+
+        let invalid = |kind: &str| SimpleError::new(format!("{} is not a valid {}", value, kind));
+
+        let cond = match field.as_str() {
+            "element" | "elem" => Condition::Element(Elements::from_str(value).map_err(|_| invalid("element"))?),
+            "blight" => Condition::Blight(Elements::from_str(value).map_err(|_| invalid("element"))?),
+            "range" => Condition::Range(Ranges::from_str(value).map_err(|_| invalid("range"))?),
+            "class" => Condition::Class(ChipClass::from_str(value).map_err(|_| invalid("chip class"))?),
+            "type" | "kind" => Condition::Kind(ChipType::from_str(value).map_err(|_| invalid("chip type"))?),
+            "skill" => Condition::Skill(Skills::from_str(value).map_err(|_| invalid("skill"))?),
+            "user" => Condition::SkillUser(Skills::from_str(value).map_err(|_| invalid("skill"))?),
+            "target" => Condition::SkillTarget(Skills::from_str(value).map_err(|_| invalid("skill"))?),
+            "check" => Condition::SkillCheck(Skills::from_str(value).map_err(|_| invalid("skill"))?),
+            "damage" | "dmg" if op == Comparison::Equal && (value == "--" || value.eq_ignore_ascii_case("none")) => {
+                Condition::NoDamage
+            }
+            "damage" | "dmg" => {
+                let average = average_damage(value).ok_or_else(|| invalid("damage, try XdY or a number"))?;
+                Condition::Damage(op, average)
+            }
+            "hits" if op == Comparison::Equal && (value == "--" || value.eq_ignore_ascii_case("none")) => {
+                Condition::NoHits
+            }
+            "hits" => Condition::Hits(op, value.parse::<u16>().map_err(|_| invalid("number of hits"))?),
+            "name" => Condition::Name(value.to_lowercase()),
+            _ => {
+                return Err(SimpleError::new(format!(
+                    "{} is not a field that can be searched, the fields are {}",
+                    field, FIELDS
+                )))
+            }
+        };
+        Ok(cond)
+    }
+
+    fn matches(&self, chip: &BattleChip) -> bool {
+        match self {
+            Condition::Element(elem) => chip.element.contains(elem),
+            Condition::Blight(elem) => chip.blight.as_ref() == Some(elem),
+            Condition::Range(range) => chip.range == *range,
+            Condition::Class(class) => chip.class == *class,
+            Condition::Kind(kind) => chip.kind == *kind,
+            // same special case as the skill command, varies means more than one possible skill
+            Condition::Skill(Skills::Varies) => chip.skills.len() > 1,
+            Condition::Skill(skill) => chip.skills.contains(skill),
+            Condition::SkillUser(skill) => chip.has_save_user(*skill),
+            Condition::SkillTarget(skill) => chip.has_save_target(*skill),
+            Condition::SkillCheck(skill) => chip.has_save_user(*skill) || chip.has_save_target(*skill),
+            Condition::NoDamage => chip.damage.is_none(),
+            Condition::Damage(op, wanted) => {
+                chip.damage.is_some_and(|damage| op.compare(damage.average(), *wanted))
+            }
+            Condition::NoHits => chip.hits.is_none(),
+            // a chip matches if any number of hits it can make does
+            Condition::Hits(op, wanted) => chip.hits.is_some_and(|hits| match op {
+                Comparison::Equal => hits.contains(*wanted),
+                Comparison::Less | Comparison::LessEqual => op.compare(hits.min, *wanted),
+                Comparison::Greater | Comparison::GreaterEqual => op.compare(hits.max, *wanted),
+            }),
+            Condition::Name(name) => chip.name.to_lowercase().contains(name),
+        }
+    }
+}
+
+/// The average of a damage roll such as 2d6, or a flat number
+fn average_damage(damage: &str) -> Option<f64> {
+    match damage.trim().parse::<u32>() {
+        Ok(flat) => Some(flat as f64),
+        Err(_) => damage.parse::<ChipDamage>().ok().map(|damage| damage.average()),
+    }
+}
+
+/// A parsed chip search, conditions combined with AND, OR, NOT and parentheses
+pub(crate) enum ChipQuery {
+    Condition(Condition),
+    Not(Box<ChipQuery>),
+    And(Box<ChipQuery>, Box<ChipQuery>),
+    Or(Box<ChipQuery>, Box<ChipQuery>),
+}
+
+impl ChipQuery {
+    pub fn matches(&self, chip: &BattleChip) -> bool {
+        match self {
+            ChipQuery::Condition(cond) => cond.matches(chip),
+            ChipQuery::Not(inner) => !inner.matches(chip),
+            ChipQuery::And(lhs, rhs) => lhs.matches(chip) && rhs.matches(chip),
+            ChipQuery::Or(lhs, rhs) => lhs.matches(chip) || rhs.matches(chip),
+        }
+    }
+}
+
+impl FromStr for ChipQuery {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<ChipQuery, SimpleError> {
+        let tokens = tokenize(to_parse)?;
+        if tokens.is_empty() {
+            return Err(SimpleError::new("You must give something to search for"));
+        }
+
+        let mut parser = QueryParser { tokens, index: 0 };
+        let query = parser.or()?;
+        match parser.peek() {
+            None => Ok(query),
+            Some(")") => Err(SimpleError::new("There is a ) without a matching (")),
+            Some(token) => Err(SimpleError::new(format!("Did not expect {} here", token))),
+        }
+    }
+}
+
+/// Splits a query into words and parentheses, keeping quoted values together
+fn tokenize(to_parse: &str) -> Result<Vec<String>, SimpleError> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in to_parse.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            _ if in_quotes => current.push(c),
+            '(' | ')' => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                tokens.push(c.to_string());
+            }
+            _ if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(SimpleError::new("There is a \" without a matching \""));
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+struct QueryParser {
+    tokens: Vec<String>,
+    index: usize,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.index).map(String::as_str)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|token| token.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Result<ChipQuery, SimpleError> {
+        let mut lhs = self.and()?;
+        while self.peek_keyword("or") {
+            self.index += 1;
+            let rhs = self.and()?;
+            lhs = ChipQuery::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    /// Conditions next to each other without a keyword are also combined with AND
+    fn and(&mut self) -> Result<ChipQuery, SimpleError> {
+        let mut lhs = self.not()?;
+        loop {
+            if self.peek_keyword("and") {
+                self.index += 1;
+            } else if self.peek().is_none() || self.peek_keyword("or") || self.peek() == Some(")") {
+                return Ok(lhs);
+            }
+            let rhs = self.not()?;
+            lhs = ChipQuery::And(Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn not(&mut self) -> Result<ChipQuery, SimpleError> {
+        if self.peek_keyword("not") {
+            self.index += 1;
+            let inner = self.not()?;
+            return Ok(ChipQuery::Not(Box::new(inner)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<ChipQuery, SimpleError> {
+        let token = match self.peek() {
+            Some(token) => token.to_owned(),
+            None => return Err(SimpleError::new("The search ended where a condition was expected")),
+        };
+        self.index += 1;
+
+        match token.to_ascii_lowercase().as_str() {
+            "(" => {
+                let inner = self.or()?;
+                if self.peek() != Some(")") {
+                    return Err(SimpleError::new("There is a ( without a matching )"));
+                }
+                self.index += 1;
+                Ok(inner)
+            }
+            ")" | "and" | "or" => Err(SimpleError::new(format!("Expected a condition, found {}", token))),
+            _ => Condition::parse(&token).map(ChipQuery::Condition),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_chips;
+
+    /// The names of the test chips a query matches, in the order they are written
+    fn search(query: &str) -> Vec<String> {
+        let query = query.parse::<ChipQuery>().unwrap_or_else(|why| panic!("{} did not parse: {}", query, why));
+        test_chips::chips()
+            .into_iter().filter(|chip| query.matches(chip)).map(|chip| chip.name).collect()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(search("element:fire or element:aqua and range:far"), vec!["FireSword"]);
+        assert_eq!(search("element:aqua and range:far or element:fire"), vec!["FireSword"]);
+        assert_eq!(search("(element:fire or element:aqua) and range:near"), vec!["AquaTower"]);
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert_eq!(search("not element:null and range:close"), vec!["FireSword", "WideSword"]);
+        assert_eq!(search("not (element:null and range:far)"), vec!["AirShot", "FireSword", "AquaTower", "Recover", "WideSword"]);
+        assert_eq!(search("not element:null or range:self"), vec!["AirShot", "FireSword", "AquaTower", "Recover", "WideSword"]);
+        assert_eq!(search("not not element:fire"), vec!["FireSword"]);
+    }
+
+    #[test]
+    fn conditions_side_by_side_are_and() {
+        assert_eq!(search("element:null type:projectile"), vec!["Cannon"]);
+        assert_eq!(search("ELEMENT:null AND type:projectile"), vec!["Cannon"]);
+        assert_eq!(search("skill:per class:mega"), vec!["AquaTower"]);
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(search("damage>=3d6"), vec!["FireSword", "WideSword"]);
+        assert_eq!(search("damage<10"), vec!["Cannon", "AirShot", "AquaTower"]);
+        assert_eq!(search("damage:--"), vec!["Recover"]);
+        assert_eq!(search("hits>1"), vec!["AquaTower"]);
+        assert_eq!(search("hits:2"), vec!["AquaTower"]);
+        assert_eq!(search("hits:none"), vec!["Recover"]);
+        assert_eq!(search("name:\"sword\""), vec!["FireSword", "WideSword"]);
+    }
+
+    #[test]
+    fn refuses_malformed_queries() {
+        for query in [
+            "",
+            "(element:fire",
+            "element:fire)",
+            "element:",
+            "element:fire or",
+            "and element:fire",
+            "not",
+            "range>near",
+            "colour:red",
+            "element:plasma",
+            "name:\"sword",
+        ] {
+            assert!(query.parse::<ChipQuery>().is_err(), "{} parsed", query);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use simple_error::SimpleError;
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy)]
 pub enum Ranges {
     #[serde(rename(serialize = "Self"))]
     Itself,
@@ -0,0 +1,61 @@
+use serde::Serialize;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use std::str::FromStr;
+
+use crate::library::battlechip::skills::Skills;
+
+static R_SAVE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"an?\s(\w+)\scheck\sof\s\[DC\s(\d+)\s\+\s(\w+)]([^.\[]*)").expect("could not compile save regex")
+});
+
+/// A check a chip forces its target to make, such as an Agility check of [DC 12 + Valor]
+#[derive(Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all(serialize = "PascalCase"))]
+pub struct ChipSave {
+    pub base: u16,
+    /// The skill of the chip's user which is added to the base
+    pub user: Skills,
+    /// The skill the target rolls against the DC
+    pub target: Skills,
+    /// What the chip says happens alongside the check, such as "or be Paralyzed for 1 turn"
+    pub effect: String,
+}
+
+impl ChipSave {
+    /// Every save in a chip's description, in the order they are written
+    pub fn parse_all(description: &str) -> Vec<ChipSave> {
+        R_SAVE
+            .captures_iter(description)
+            .filter_map(|save| {
+                Some(ChipSave {
+                    base: save.get(2)?.as_str().parse().ok()?,
+                    user: Skills::from_str(save.get(3)?.as_str()).unwrap_or_default(),
+                    target: Skills::from_str(save.get(1)?.as_str()).unwrap_or_default(),
+                    effect: save
+                        .get(4)
+                        .map(|effect| effect.as_str().trim_matches(|c: char| c.is_whitespace() || c == ','))
+                        .unwrap_or_default()
+                        .to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// The DC the target must meet when the chip's user has that value in the user skill
+    pub fn dc(&self, user_value: i64) -> i64 {
+        self.base as i64 + user_value
+    }
+}
+
+impl std::fmt::Display for ChipSave {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} check of [DC {} + {}]", self.target, self.base, self.user)?;
+        if !self.effect.is_empty() {
+            write!(f, " {}", self.effect)?;
+        }
+        Ok(())
+    }
+}
@@ -88,4 +88,44 @@ impl Skills {
             Skills::Varies => "VAR",
         }
     }
-}
\ No newline at end of file
+
+    /// The attribute whose dice are rolled when making a check with this skill
+    pub(crate) fn attribute(&self) -> Option<Attributes> {
+        match self {
+            Skills::Perception | Skills::Info | Skills::Tech => Some(Attributes::Mind),
+            Skills::Strength | Skills::Agility | Skills::Endurance => Some(Attributes::Body),
+            Skills::Charm | Skills::Valor | Skills::Affinity => Some(Attributes::Spirit),
+            Skills::None | Skills::Varies => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Attributes {
+    Mind,
+    Body,
+    Spirit,
+}
+
+impl std::str::FromStr for Attributes {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<Attributes, SimpleError> {
+        match to_parse.to_ascii_lowercase().as_str() {
+            "mind" | "mnd" => Ok(Attributes::Mind),
+            "body" | "bdy" => Ok(Attributes::Body),
+            "spirit" | "spr" => Ok(Attributes::Spirit),
+            _ => Err(SimpleError::new("could not parse attribute")),
+        }
+    }
+}
+
+impl std::fmt::Display for Attributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Attributes::Mind => write!(f, "Mind"),
+            Attributes::Body => write!(f, "Body"),
+            Attributes::Spirit => write!(f, "Spirit"),
+        }
+    }
+}
@@ -7,10 +7,19 @@ use std::error::Error;
 use tokio::sync::RwLock;
 use serde_json::{Value, json};
 
+use crate::library::keywords::KeywordIndex;
+
 
 pub trait StatusLike {
     fn get_values(&self) -> &Value;
 
+    fn keys(&self) -> Vec<&str> {
+        match self.get_values().as_object() {
+            Some(list) => list.keys().map(String::as_str).collect(),
+            None => vec![],
+        }
+    }
+
     fn to_slash_opts(&self) -> Value {
 
         let obj = match self.get_values().as_object() {
@@ -132,9 +141,10 @@ impl TypeMapKey for Statuses {
 }
 
 #[command("status")]
-/// Get info on what a status means
+/// Get info on what a status means, add users to list every chip and virus which inflicts it
 #[example = "Blind"]
-pub(crate) async fn get_status(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+#[example = "Blind users"]
+pub(crate) async fn get_status(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     if args.is_empty() {
         reply!(ctx, msg, "you must provide a status");
         return Ok(());
@@ -143,7 +153,22 @@ pub(crate) async fn get_status(ctx: &Context, msg: &Message, args: Args) -> Comm
     let data = ctx.data.read().await;
     let status_lock = data.get::<Statuses>().expect("statuses not found");
     let statuses = status_lock.read().await;
-    let res = statuses.get(args.current().unwrap()); //.unwrap_or("There is no blight with that element, perhaps you spelled it wrong?");
+    let status = args.single::<String>()?;
+
+    if args.current().is_some_and(|arg| arg.eq_ignore_ascii_case("users")) {
+        if statuses.get(&status).is_none() {
+            reply!(ctx, msg, "There is no status with that name, perhaps you spelled it wrong?");
+            return Ok(());
+        }
+        let keywords = data.get::<KeywordIndex>().expect("keyword index not found").read().await;
+        match keywords.status_users(&status) {
+            Some(users) => long_say!(ctx, msg, users, ", "),
+            None => reply!(ctx, msg, format!("No chips or viruses mention {}", status)),
+        }
+        return Ok(());
+    }
+
+    let res = statuses.get(&status); //.unwrap_or("There is no blight with that element, perhaps you spelled it wrong?");
     let to_send = match res {
         Some(val) => format!("```{}```", val),
         None => String::from("There is no status with that name, perhaps you spelled it wrong?"),
@@ -0,0 +1,378 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::{channel::Message, id::ChannelId},
+    prelude::*,
+};
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use simple_error::SimpleError;
+
+use crate::{
+    bot_data::BotData,
+    library::{chip_library::ChipLibrary, ncp_library::NCPLibrary, virus_library::VirusLibrary, Library, LibraryObject},
+    util::send_long_message_to,
+};
+
+const CHANGELOG_FILE: &str = "./changelog.json";
+
+/// How many reloads worth of changes are kept
+const MAX_RELOADS: usize = 50;
+
+/// Fields which hold the whole entry as written, so always change with everything else,
+/// and the typed copies of chip damage and hits, whose written forms are already compared
+const SKIPPED_FIELDS: [&str; 4] = ["All", "all", "DamageRoll", "HitRange"];
+
+/// Fields too long to show both versions of
+const LONG_FIELDS: [&str; 2] = ["Description", "description"];
+
+/// Every entry in a library by name, with its fields as JSON
+pub(crate) struct LibrarySnapshot {
+    kind: &'static str,
+    entries: BTreeMap<String, Value>,
+}
+
+impl LibrarySnapshot {
+    fn new<'a, T, I>(kind: &'static str, objects: I) -> LibrarySnapshot
+    where
+        T: Serialize + LibraryObject + 'a,
+        I: Iterator<Item = &'a Arc<T>>,
+    {
+        let entries = objects
+            .filter_map(|obj| Some((obj.get_name().to_owned(), serde_json::to_value(obj).ok()?)))
+            .collect();
+        LibrarySnapshot { kind, entries }
+    }
+
+    /// Every change from this snapshot to a newer one
+    fn diff(&self, newer: &LibrarySnapshot) -> Vec<Change> {
+        let mut changes = vec![];
+        for (name, old) in &self.entries {
+            match newer.entries.get(name) {
+                None => changes.push(Change::new(self.kind, name, ChangeKind::Removed)),
+                Some(new) if new != old => {
+                    let fields = diff_fields(old, new);
+                    if !fields.is_empty() {
+                        changes.push(Change::new(self.kind, name, ChangeKind::Modified(fields)));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        for name in newer.entries.keys().filter(|name| !self.entries.contains_key(*name)) {
+            changes.push(Change::new(self.kind, name, ChangeKind::Added));
+        }
+        changes
+    }
+}
+
+/// Snapshots of all three libraries, taken before a reload to compare against afterwards
+pub(crate) struct Snapshots([LibrarySnapshot; 3]);
+
+impl Snapshots {
+    pub async fn take(data: &TypeMap) -> Snapshots {
+        let chips = data.get::<ChipLibrary>().expect("chip library not found").read().await;
+        let ncps = data.get::<NCPLibrary>().expect("ncp library not found").read().await;
+        let viruses = data.get::<VirusLibrary>().expect("virus library not found").read().await;
+        Snapshots([
+            LibrarySnapshot::new("Chip", chips.get_collection().values()),
+            LibrarySnapshot::new("NCP", ncps.get_collection().values()),
+            LibrarySnapshot::new("Virus", viruses.get_collection().values()),
+        ])
+    }
+
+    pub fn diff(&self, newer: &Snapshots) -> Vec<Change> {
+        self.0
+            .iter()
+            .zip(newer.0.iter())
+            .flat_map(|(old, new)| old.diff(new))
+            .collect()
+    }
+}
+
+/// A field which is different between two versions of an entry
+#[derive(Serialize, Deserialize)]
+pub(crate) struct FieldChange {
+    field: String,
+    old: Option<String>,
+    new: Option<String>,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "{} {} → {}", self.field, old, new),
+            (None, Some(new)) => write!(f, "{} added: {}", self.field, new),
+            (Some(old), None) => write!(f, "{} removed: {}", self.field, old),
+            (None, None) => write!(f, "{} changed", self.field),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ChangeKind {
+    Added,
+    Removed,
+    Modified(Vec<FieldChange>),
+}
+
+/// A single entry which was added, removed, or modified by a reload
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Change {
+    kind: String,
+    name: String,
+    change: ChangeKind,
+}
+
+impl Change {
+    fn new(kind: &str, name: &str, change: ChangeKind) -> Change {
+        Change { kind: kind.to_owned(), name: name.to_owned(), change }
+    }
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.change {
+            ChangeKind::Added => write!(f, "+ {} ({}) added", self.name, self.kind),
+            ChangeKind::Removed => write!(f, "- {} ({}) removed", self.name, self.kind),
+            ChangeKind::Modified(fields) => {
+                write!(f, "~ {} ({}): ", self.name, self.kind)?;
+                let fields = fields.iter().map(FieldChange::to_string).collect::<Vec<String>>();
+                write!(f, "{}", fields.join("; "))
+            }
+        }
+    }
+}
+
+fn diff_fields(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let (old, new) = match (old.as_object(), new.as_object()) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return vec![],
+    };
+
+    let mut fields = old.keys().chain(new.keys().filter(|key| !old.contains_key(*key))).collect::<Vec<&String>>();
+    fields.retain(|field| !SKIPPED_FIELDS.contains(&field.as_str()));
+
+    fields
+        .into_iter()
+        .filter(|field| old.get(*field) != new.get(*field))
+        .map(|field| {
+            let long = LONG_FIELDS.contains(&field.as_str());
+            FieldChange {
+                field: field_name(field),
+                old: old.get(field).filter(|_| !long).and_then(show_value),
+                new: new.get(field).filter(|_| !long).and_then(show_value),
+            }
+        })
+        .collect()
+}
+
+/// Turns a serialized field name like SkillUser or e_b_cost into words
+fn field_name(field: &str) -> String {
+    let mut to_ret = String::with_capacity(field.len() + 2);
+    let mut prev_lower = false;
+    for c in field.chars() {
+        if c == '_' || (c.is_uppercase() && prev_lower) {
+            to_ret.push(' ');
+        }
+        if c != '_' {
+            to_ret.extend(c.to_lowercase());
+        }
+        prev_lower = c.is_lowercase();
+    }
+    to_ret
+}
+
+fn show_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        Value::Array(values) => Some(values.iter().filter_map(show_value).collect::<Vec<String>>().join(", ")),
+        Value::Object(map) => Some(
+            map.iter()
+                .filter_map(|(key, val)| Some(format!("{}: {}", key, show_value(val)?)))
+                .collect::<Vec<String>>()
+                .join(", "),
+        ),
+        other => Some(other.to_string()),
+    }
+}
+
+/// The changes made by one reload
+#[derive(Serialize, Deserialize)]
+struct ChangelogEntry {
+    timestamp: i64,
+    changes: Vec<Change>,
+}
+
+/// The changes made by past reloads, oldest first
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Changelog(Vec<ChangelogEntry>);
+
+impl Changelog {
+    /// Loads the saved changelog, starting fresh if there is none yet
+    pub async fn import() -> RwLock<Changelog> {
+        let changelog = match tokio::fs::read_to_string(CHANGELOG_FILE).await {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|why| {
+                eprintln!("Could not parse {}, starting with no changelog: {}", CHANGELOG_FILE, why);
+                Changelog::default()
+            }),
+            Err(_) => Changelog::default(),
+        };
+        RwLock::new(changelog)
+    }
+
+    async fn save(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string(&self.0)?;
+        tokio::fs::write(CHANGELOG_FILE, json).await?;
+        Ok(())
+    }
+
+    fn since(&self, since: DateTime<Utc>) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|entry| entry.timestamp >= since.timestamp())
+            .flat_map(|entry| {
+                let time = Utc.timestamp_opt(entry.timestamp, 0).single().unwrap_or_else(Utc::now);
+                std::iter::once(format!("**Reload at {}**", time.format("%Y-%m-%d %H:%M UTC")))
+                    .chain(entry.changes.iter().map(Change::to_string))
+            })
+            .collect()
+    }
+}
+
+impl TypeMapKey for Changelog {
+    type Value = RwLock<Changelog>;
+}
+
+/// Records the changes a reload made and posts them to the announcements channel if there is one,
+/// returns a summary for the reload message
+pub(crate) async fn record_changes(ctx: &Context, data: &TypeMap, before: Snapshots) -> String {
+    let after = Snapshots::take(data).await;
+    let changes = before.diff(&after);
+    if changes.is_empty() {
+        return String::from("No entries changed");
+    }
+
+    let count = changes.len();
+    let lines = changes.iter().map(Change::to_string).collect::<Vec<String>>();
+
+    let changelog_lock = data.get::<Changelog>().expect("changelog not found");
+    let mut changelog = changelog_lock.write().await;
+    changelog.0.push(ChangelogEntry { timestamp: Utc::now().timestamp(), changes });
+    if changelog.0.len() > MAX_RELOADS {
+        let extra = changelog.0.len() - MAX_RELOADS;
+        changelog.0.drain(..extra);
+    }
+    if let Err(why) = changelog.save().await {
+        eprintln!("Could not save changelog: {:?}", why);
+    }
+
+    let config = data.get::<BotData>().expect("no bot data available");
+    if config.changelog_channel != 0 {
+        let to_send = std::iter::once(String::from("**Library changes**")).chain(lines);
+        if let Err(why) = send_long_message_to(ctx, ChannelId(config.changelog_channel), to_send, "\n").await {
+            println!("Could not post changelog: {:?}", why);
+        }
+    }
+
+    format!("{} entries changed, see the changes command for what", count)
+}
+
+/// Parses how far back to show changes, either a date like 2021-06-01 or a duration like 12h, 3d, or 2w
+fn parse_since(to_parse: &str) -> Result<DateTime<Utc>, SimpleError> {
+    if let Ok(date) = NaiveDate::parse_from_str(to_parse, "%Y-%m-%d") {
+        let start = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+        return Ok(Utc.from_utc_datetime(&start));
+    }
+
+    let invalid = || {
+        SimpleError::new(format!(
+            "{} is not a date or a duration, try 2021-06-01, 12h, 3d, or 2w",
+            to_parse
+        ))
+    };
+    let (split, unit) = to_parse.char_indices().next_back().ok_or_else(invalid)?;
+    let amount = to_parse[..split].parse::<i64>().map_err(|_| invalid())?;
+    let unit_millis = match unit.to_ascii_lowercase() {
+        'h' => 60 * 60 * 1000,
+        'd' => 24 * 60 * 60 * 1000,
+        'w' => 7 * 24 * 60 * 60 * 1000,
+        _ => return Err(invalid()),
+    };
+    // a huge amount would overflow the duration or the date, treat it like any other bad input
+    let millis = amount.checked_mul(unit_millis).ok_or_else(invalid)?;
+    Utc::now()
+        .checked_sub_signed(Duration::milliseconds(millis))
+        .ok_or_else(invalid)
+}
+
+#[command("changes")]
+#[aliases("changelog")]
+/// See what changed in the chips, NCPs, and viruses when they were reloaded
+/// Give a date or how long ago to see every change since then, otherwise only the last reload is shown
+#[example = "3d"]
+#[example = "2021-06-01"]
+pub(crate) async fn changes(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let changelog_lock = data.get::<Changelog>().expect("changelog not found");
+    let changelog = changelog_lock.read().await;
+
+    let since = match args.rest().trim() {
+        "" => match changelog.0.last() {
+            Some(entry) => Utc.timestamp_opt(entry.timestamp, 0).single().unwrap_or_else(Utc::now),
+            None => {
+                reply!(ctx, msg, "No changes have been recorded yet");
+                return Ok(());
+            }
+        },
+        since => match parse_since(since) {
+            Ok(since) => since,
+            Err(why) => {
+                reply!(ctx, msg, why);
+                return Ok(());
+            }
+        },
+    };
+
+    let to_send = changelog.since(since);
+    if to_send.is_empty() {
+        reply!(ctx, msg, "Nothing has changed since then");
+        return Ok(());
+    }
+    long_say!(ctx, msg, to_send, "\n");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates_and_durations() {
+        assert_eq!(parse_since("2021-06-01").unwrap(), Utc.ymd(2021, 6, 1).and_hms(0, 0, 0));
+
+        let since = parse_since("3d").unwrap();
+        let expected = Utc::now() - Duration::days(3);
+        assert!((since - expected).num_seconds().abs() < 5);
+        assert!(parse_since("2W").is_ok());
+    }
+
+    #[test]
+    fn refuses_a_non_ascii_unit() {
+        assert!(parse_since("3д").is_err());
+        assert!(parse_since("д").is_err());
+        assert!(parse_since("").is_err());
+    }
+
+    #[test]
+    fn refuses_a_huge_amount() {
+        assert!(parse_since("9223372036854775807w").is_err());
+        assert!(parse_since("9999999999999h").is_err());
+        assert!(parse_since("-9223372036854775807d").is_err());
+    }
+}
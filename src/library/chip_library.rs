@@ -8,10 +8,7 @@ use serenity::{
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::{RwLock, RwLockReadGuard};
 
-use rand::{
-    distributions::{Distribution, Uniform},
-    rngs::ThreadRng,
-};
+use rand::{rngs::ThreadRng, seq::SliceRandom};
 
 use itertools::Itertools;
 
@@ -19,12 +16,23 @@ use simple_error::SimpleError;
 
 use crate::{
     bot_data::BotData,
+    dice::DieRoll,
     library::{
-        battlechip::{skills::Skills, BattleChip},
+        battlechip::{
+            chip_type::{ChipClass, ChipType},
+            query::ChipQuery,
+            ranges::Ranges,
+            skills::Skills,
+            BattleChip, ChipParseError,
+        },
         elements::Elements,
+        folder::ChipFolder,
+        homebrew::{reply_homebrew, HomebrewChips},
+        render::Renderer,
         Library,
         virus_library::VirusLibrary,
     },
+    util::split_last,
     LibraryObject,
     ReloadReturnType,
 };
@@ -35,6 +43,83 @@ pub struct ChipLibrary {
     chips: HashMap<String, Arc<BattleChip>>,
     chip_url: String,
     custom_chip_url: String,
+    diagnostics: Vec<ChipDiagnostic>,
+}
+
+/// A chip entry which could not be parsed, and where it was
+pub struct ChipDiagnostic {
+    pub source: &'static str,
+    pub line: usize,
+    pub raw: String,
+    pub error: ChipParseError,
+}
+
+impl std::fmt::Display for ChipDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} line {}, failed at {}\n    {}", self.source, self.line, self.error, self.raw)
+    }
+}
+
+/// Parses every chip in some text, skipping to the next chip after a bad one
+pub(crate) fn parse_chip_text(text: &str, source: &'static str) -> (Vec<BattleChip>, Vec<ChipDiagnostic>) {
+    let lines = text
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect::<Vec<(usize, &str)>>();
+
+    let mut chips = vec![];
+    let mut diagnostics = vec![];
+    let mut index = 0;
+    while index < lines.len() {
+        let (line, first_line) = lines[index];
+        let second_line = lines
+            .get(index + 1)
+            .map(|(_, second_line)| *second_line)
+            .filter(|second_line| !BattleChip::is_chip_header(second_line));
+
+        let res = match second_line {
+            Some(second_line) if BattleChip::is_chip_header(first_line) => {
+                BattleChip::from_chip_string(first_line, second_line)
+            }
+            None if BattleChip::is_chip_header(first_line) => Err(ChipParseError {
+                field: "description",
+                reason: String::from("chip has no description line"),
+            }),
+            _ => BattleChip::from_chip_string(first_line, ""),
+        };
+
+        match res {
+            Ok(chip) => {
+                chips.push(chip);
+                index += 2;
+            }
+            Err(error) => {
+                diagnostics.push(ChipDiagnostic { source, line, raw: first_line.to_owned(), error });
+                // resynchronise on the next line which looks like the start of a chip
+                index += 1;
+                while index < lines.len() && !BattleChip::is_chip_header(lines[index].1) {
+                    index += 1;
+                }
+            }
+        }
+    }
+    (chips, diagnostics)
+}
+
+#[cfg(test)]
+impl ChipLibrary {
+    /// A library holding only the chips in some text, for tests which need one
+    pub(crate) fn from_chip_text(text: &str) -> ChipLibrary {
+        let mut library = ChipLibrary::new("", "");
+        let (chips, diagnostics) = parse_chip_text(text, "test");
+        for chip in chips {
+            library.chips.insert(chip.name.to_lowercase(), Arc::new(chip));
+        }
+        library.diagnostics = diagnostics;
+        library
+    }
 }
 
 impl Library for ChipLibrary {
@@ -51,6 +136,7 @@ impl ChipLibrary {
             chips: HashMap::new(),
             chip_url: String::from(url),
             custom_chip_url: String::from(custom_url),
+            diagnostics: vec![],
         }
     }
 
@@ -64,7 +150,13 @@ impl ChipLibrary {
         let config = data_lock.get::<BotData>().expect("bot data not found");
         let mut chip_library = chip_library_lock.write().await;
         let chip_reload_str = chip_library.load_chips(config.load_custom_chips).await?;
-        str_to_ret = format!("{} chips loaded\n", chip_reload_str);
+        str_to_ret = match chip_library.diagnostics.len() {
+            0 => format!("{} chips loaded\n", chip_reload_str),
+            bad => format!(
+                "{} chips loaded, {} chip entries could not be parsed and were skipped\n",
+                chip_reload_str, bad
+            ),
+        };
         vec_to_ret.reserve(chip_library.get_collection().len());
         for val in chip_library.get_collection().values() {
             let trait_obj = battlechip_as_lib_obj(Arc::clone(val));
@@ -85,40 +177,15 @@ impl ChipLibrary {
             tokio::join!(chip_text_future, custom_chip_text_future);
 
         let chip_text = chip_text_res?;
-        self.chips = tokio::task::spawn_blocking(move || {
-            let mut chip_text_arr: Vec<&str> = chip_text
-            .split('\n')
-            .filter(|&i| !i.trim().is_empty())
-            .collect();
-        let special_chip_text;
-        if special_chips_res.is_some() {
-            special_chip_text = special_chips_res.unwrap();
-            let mut special_chip_arr: Vec<&str> = special_chip_text
-                .split('\n')
-                .filter(|&i| !i.trim().is_empty())
-                .collect();
-            chip_text_arr.append(&mut special_chip_arr);
-        }
-            let mut chips: Vec<BattleChip> = vec![];
-            for val in chip_text_arr
-                .iter()
-                .step_by(2)
-                .zip(chip_text_arr.iter().skip(1).step_by(2))
-            {
-                let first_line = val.0;
-                let second_line = val.1;
-                let chip = match BattleChip::from_chip_string(first_line, second_line) {
-                    Ok(val) => val,
-                    Err(e) => {
-                        return Err(SimpleError::new(format!(
-                            "Found an invalid chip:{} {}",
-                            first_line,
-                            e.as_str(),
-                        )))
-                    }
-                };
-                chips.push(chip);
+        let (chips, diagnostics) = tokio::task::spawn_blocking(move || {
+            let (mut chips, mut diagnostics) = parse_chip_text(&chip_text, "chips");
+            if let Some(special_chip_text) = special_chips_res {
+                let (mut special_chips, mut special_diagnostics) =
+                    parse_chip_text(&special_chip_text, "custom chips");
+                chips.append(&mut special_chips);
+                diagnostics.append(&mut special_diagnostics);
             }
+
             chips.shrink_to_fit();
             chips.sort_unstable();
 
@@ -133,22 +200,30 @@ impl ChipLibrary {
             for chip in chips.drain(..) {
                 new_chips.insert(chip.name.to_lowercase(), Arc::new(chip));
             }
-            
-            Ok(new_chips)
-        }).await??;
 
+            (new_chips, diagnostics)
+        }).await?;
+
+        self.chips = chips;
+        self.diagnostics = diagnostics;
         Ok(self.chips.len())
     }
 
+    /// Every chip entry which could not be parsed on the last load
+    pub fn diagnostics(&self) -> &[ChipDiagnostic] {
+        &self.diagnostics
+    }
+
     async fn get_chip_text(url: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let res = reqwest::get(url)
-            .await?
-            .text()
-            .await?
-            .replace("\u{e2}\u{20ac}\u{2122}", "'")
+        let res = reqwest::get(url).await?.text().await?;
+        Ok(ChipLibrary::clean_chip_text(&res))
+    }
+
+    /// Fixes up the characters the chip documents are exported with
+    pub(crate) fn clean_chip_text(text: &str) -> String {
+        text.replace("\u{e2}\u{20ac}\u{2122}", "'")
             .replace("\u{FEFF}", "")
-            .replace("\r", "");
-        Ok(res)
+            .replace("\r", "")
     }
 
     async fn get_custom_chip_text(url: &str, load_custom_chips: bool) -> Option<String> {
@@ -157,14 +232,8 @@ impl ChipLibrary {
         }
         
         let special_chips_res = reqwest::get(url).await.ok()?;
-        let res = special_chips_res
-            .text()
-            .await
-            .ok()?
-            .replace("\u{e2}\u{20ac}\u{2122}", "'")
-            .replace("\u{FEFF}", "")
-            .replace("\r", "");
-        Some(res)
+        let res = special_chips_res.text().await.ok()?;
+        Some(ChipLibrary::clean_chip_text(&res))
     }
 
     pub fn search_element(&self, to_get: &str) -> Option<Vec<&Arc<BattleChip>>> {
@@ -189,13 +258,13 @@ impl ChipLibrary {
     pub fn search_skill_target(&self, to_get: &str) -> Option<Vec<&Arc<BattleChip>>> {
         let skill_to_get = Skills::from_str(to_get).ok()?;
 
-        self.search_any(skill_to_get, |a, b| a.skill_target == b)
+        self.search_any(skill_to_get, |a, b| a.has_save_target(b))
     }
 
     pub fn search_skill_user(&self, to_get: &str) -> Option<Vec<&Arc<BattleChip>>> {
         let skill_to_get = Skills::from_str(to_get).ok()?;
 
-        self.search_any(skill_to_get, |a, b| a.skill_user == b)
+        self.search_any(skill_to_get, |a, b| a.has_save_user(b))
     }
 
     pub fn search_skill_check(&self, to_get: &str) -> Option<Vec<&Arc<BattleChip>>> {
@@ -203,11 +272,34 @@ impl ChipLibrary {
         let skill_to_get = Skills::from_str(to_get).ok()?;
 
         self.search_any(skill_to_get, |a, b| {
-            a.skill_target == b || a.skill_user == b
+            a.has_save_target(b) || a.has_save_user(b)
+        })
+    }
+
+    pub fn search_class(&self, class: ChipClass, element: Option<Elements>) -> Option<Vec<&Arc<BattleChip>>> {
+        self.search_any((class, element), |a, (class, element)| {
+            a.class == class && has_element(a, element)
+        })
+    }
+
+    pub fn search_type(&self, kind: ChipType, element: Option<Elements>) -> Option<Vec<&Arc<BattleChip>>> {
+        self.search_any((kind, element), |a, (kind, element)| {
+            a.kind == kind && has_element(a, element)
+        })
+    }
+
+    pub fn search_range(&self, range: Ranges, element: Option<Elements>) -> Option<Vec<&Arc<BattleChip>>> {
+        self.search_any((range, element), |a, (range, element)| {
+            a.range == range && has_element(a, element)
         })
     }
 }
 
+/// true if no element was given, or the chip is of the given element
+fn has_element(chip: &BattleChip, element: Option<Elements>) -> bool {
+    element.is_none_or(|elem| chip.element.contains(&elem))
+}
+
 impl TypeMapKey for ChipLibrary {
     type Value = RwLock<ChipLibrary>;
 }
@@ -220,7 +312,7 @@ pub(crate) fn battlechip_as_lib_obj(obj: Arc<BattleChip>) -> Arc<dyn LibraryObje
 #[group]
 #[prefixes("c", "chip")]
 #[default_command(send_chip)]
-#[commands(send_chip, send_chip_element, chip_drop_cr, send_chip_blight, random_chip)]
+#[commands(send_chip, send_chip_element, chip_drop_cr, send_chip_blight, random_chip, roll_chip_damage, chip_save_dc, search_chips, top_chips, send_chip_class, send_chip_type, send_chip_range, compare_chips)]
 /// A group of commands related to Navi-Customizer Parts, see `c chip` for the get chip command help
 struct BnbChips;
 
@@ -250,37 +342,263 @@ async fn send_chip(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
     let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
     let library = library_lock.read().await;
 
-    library.reaction_name_search(ctx, msg, to_get).await;
+    let renderer = Renderer::new(&data, msg.author.id, msg.guild_id).await;
+    if reply_homebrew(ctx, msg, &data, &renderer, to_get, library.get(to_get).is_some()).await {
+        return Ok(());
+    }
+
+    library.reaction_name_search(ctx, msg, to_get, &renderer).await;
     Ok(())
 }
 
+#[command("dc")]
+#[aliases("save")]
+/// get the DC a target must meet for each check a chip forces, given the user's value in the skill added to it
+#[example = "Paralyzer 3"]
+async fn chip_save_dc(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.len() < 2 {
+        reply!(ctx, msg, "you must provide a chip name and the user's skill value");
+        return Ok(());
+    }
+
+    let (to_get, user_value) = match split_last::<i64>(args.rest()) {
+        (to_get, Some(user_value)) => (to_get, user_value),
+        (_, None) => {
+            reply!(ctx, msg, "the user's skill value must be a whole number");
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let homebrew = homebrew_lock.read().await;
+
+    let chip = match homebrew.search(msg.guild_id, &library, to_get) {
+        Ok(chip) => chip,
+        Err(chips) => {
+            let to_say = chips.iter().map(|a| a.get_name()).collect::<Vec<&str>>().join(", ");
+            reply!(ctx, msg, format!("Did you mean: {}", to_say));
+            return Ok(());
+        }
+    };
+
+    if chip.saves.is_empty() {
+        reply!(ctx, msg, format!("{} does not force any checks", chip.name));
+        return Ok(());
+    }
+
+    let lines = chip
+        .saves
+        .iter()
+        .map(|save| {
+            let mut line = format!(
+                "{} check of **DC {}** ({} + {} {})",
+                save.target,
+                save.dc(user_value),
+                save.base,
+                save.user,
+                user_value
+            );
+            if !save.effect.is_empty() {
+                line.push_str(&format!(" {}", save.effect));
+            }
+            line
+        })
+        .join("\n");
+    reply!(ctx, msg, format!("{}:\n{}", chip.name, lines));
+    Ok(())
+}
+
+#[command("dmg")]
+#[aliases("damage")]
+/// roll the damage of a chip once for each hit, by default the most hits the chip can make are rolled
+#[example = "Cannon"]
+#[example = "Vulcan 2"]
+async fn roll_chip_damage(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "you must provide a name");
+        return Ok(());
+    }
+
+    // chip names can have spaces, so the number of hits is only ever the last word
+    let (to_get, hits_arg) = split_last::<u16>(args.rest());
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let homebrew = homebrew_lock.read().await;
+
+    let chip = match homebrew.search(msg.guild_id, &library, to_get) {
+        Ok(chip) => chip,
+        Err(chips) => {
+            let to_say = chips.iter().map(|a| a.get_name()).collect::<Vec<&str>>().join(", ");
+            reply!(ctx, msg, format!("Did you mean: {}", to_say));
+            return Ok(());
+        }
+    };
+
+    let (damage, chip_hits) = match (chip.damage, chip.hits) {
+        (Some(damage), Some(hits)) => (damage, hits),
+        _ => {
+            reply!(ctx, msg, format!("{} does not deal damage", chip.name));
+            return Ok(());
+        }
+    };
+
+    let hits = hits_arg.unwrap_or(chip_hits.max);
+    if !chip_hits.contains(hits) {
+        reply!(
+            ctx,
+            msg,
+            format!("{} can only hit {} times", chip.name, chip_hits)
+        );
+        return Ok(());
+    }
+
+    let to_roll = damage.to_string();
+    let mut total = 0;
+    let mut hit_lines = Vec::with_capacity(hits as usize);
+    for hit in 1..=hits {
+        let res = DieRoll::roll_dice(&to_roll, false)?;
+        total += res.total;
+        hit_lines.push(format!("Hit {}: {} {}", hit, res.total, res.breakdown));
+    }
+
+    let mut reply = format!(
+        "{} ({}) - {} damage x{}\n{}\nTotal: {}",
+        chip.name,
+        chip.element.iter().format(", "),
+        damage,
+        hits,
+        hit_lines.join("\n"),
+        total
+    );
+
+    if let Some(blight) = chip.blight {
+        reply.push_str(&format!("\nInflicts a {} blight", blight));
+    }
+
+    reply!(ctx, msg, reply);
+    Ok(())
+}
+
+/// The most chips the random command will give at once
+const MAX_RANDOM_CHIPS: usize = 10;
+
+/// Turns the plain words the other search commands take, such as fire, mega, or agility,
+/// into a chip query, leaving anything already written as a query alone
+fn random_filter(words: &[&str]) -> Result<Option<ChipQuery>, SimpleError> {
+    if words.is_empty() {
+        return Ok(None);
+    }
+
+    let mut conditions = Vec::with_capacity(words.len());
+    for word in words {
+        let lower = word.to_ascii_lowercase();
+        let condition = if word.contains([':', '<', '>', '=', '(', ')']) || ["and", "or", "not"].contains(&lower.as_str()) {
+            word.to_string()
+        } else if Elements::from_str(word).is_ok() {
+            format!("element:{}", word)
+        } else if ChipClass::from_str(word).is_ok() {
+            format!("class:{}", word)
+        } else if Skills::from_str(word).is_ok() {
+            format!("skill:{}", word)
+        } else {
+            return Err(SimpleError::new(format!(
+                "{} is not an element, chip class, or skill",
+                word
+            )));
+        };
+        conditions.push(condition);
+    }
+    conditions.join(" ").parse::<ChipQuery>().map(Some)
+}
+
 #[command("random")]
-/// get a random chip from the library
-async fn random_chip(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+/// get random chips from the library, optionally only those with an element, class, or skill
+/// give a number to get more than one, or use folder and a size to get a random legal folder
+#[example = ""]
+#[example = "fire"]
+#[example = "3 mega"]
+#[example = "folder 20 aqua"]
+async fn random_chip(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut words = args.rest().split_whitespace().collect::<Vec<&str>>();
+    let folder_mode = words.first().is_some_and(|word| word.eq_ignore_ascii_case("folder"));
+    if folder_mode {
+        words.remove(0);
+    }
+
+    let count = match words.first().map(|word| word.parse::<usize>()) {
+        Some(Ok(count)) => {
+            words.remove(0);
+            Some(count)
+        }
+        Some(Err(_)) | None => None,
+    };
+
+    let filter = match random_filter(&words) {
+        Ok(filter) => filter,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
     let data = ctx.data.read().await;
     let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
     let library = library_lock.read().await;
 
-    let lib_col = library.get_collection();
+    let chip_arr = match &filter {
+        Some(filter) => library.search_any(filter, |chip, filter| filter.matches(chip)).unwrap_or_default(),
+        None => library.get_collection().values().collect_vec(),
+    };
+
+    if chip_arr.is_empty() {
+        reply!(ctx, msg, "There are no chips like that");
+        return Ok(());
+    }
 
-    let chip_arr = lib_col.values().collect_vec();
+    if folder_mode {
+        let rules = &data.get::<BotData>().expect("no bot data available").folder_rules;
+        let size = count.unwrap_or(rules.max_size);
+        // separate block because send/sync issues
+        let folder = {
+            let mut rng = ThreadRng::default();
+            ChipFolder::random(&chip_arr, size, rules, &library, &mut rng)
+        };
+        let to_send = match folder {
+            Ok(folder) if folder.size() < size => format!(
+                "Only {} chips like that could be fit in a legal folder\n{}",
+                folder.size(),
+                folder
+            ),
+            Ok(folder) => folder.to_string(),
+            Err(why) => why.to_string(),
+        };
+        reply!(ctx, msg, to_send);
+        return Ok(());
+    }
+
+    let count = count.unwrap_or(1);
+    if !(1..=MAX_RANDOM_CHIPS).contains(&count) {
+        reply!(ctx, msg, format!("You can get from 1 to {} random chips at a time", MAX_RANDOM_CHIPS));
+        return Ok(());
+    }
 
     // separate block because send/sync issues
-    let index = {
-        let len = chip_arr.len();
+    let chosen = {
         let mut rng = ThreadRng::default();
-        let unif = Uniform::from(0..len);
-        unif.sample(&mut rng)
+        chip_arr.choose_multiple(&mut rng, count).collect_vec()
     };
 
-    let resp = match chip_arr.get(index) {
-        Some(chip) => {
-            &chip.name
-        },
-        None => "An unknown error occurred, inform Major",
-    };
-
-    reply!(ctx, msg, resp);
+    if let [chip] = chosen.as_slice() {
+        reply!(ctx, msg, chip);
+    } else {
+        long_say!(ctx, msg, chosen, "\n\n");
+    }
 
     Ok(())
 }
@@ -402,6 +720,319 @@ async fn send_chip_element(ctx: &Context, msg: &Message, args: Args) -> CommandR
     Ok(())
 }
 
+#[command("search")]
+/// get a list of chips matching a search made of field:value conditions
+/// conditions may be combined with AND, OR, NOT and parentheses, conditions next to each other must all match
+/// the fields are element, blight, range, class, type, skill, user, target, check, damage, hits and name
+/// damage and hits may also be compared with <, <=, > and >=, damage is compared by its average
+#[example = "element:fire range:near"]
+#[example = "(class:mega OR class:giga) AND type:melee"]
+#[example = "skill:str damage>=2d6 hits>1 NOT blight:aqua"]
+async fn search_chips(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "you must provide something to search for");
+        return Ok(());
+    }
+
+    let query = match args.rest().parse::<ChipQuery>() {
+        Ok(query) => query,
+        Err(why) => {
+            reply!(ctx, msg, why.as_str(), false);
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+
+    match library.search_any(&query, |chip, query| query.matches(chip)) {
+        Some(chips) => {
+            let to_send = chips.iter().map(|a| a.get_name()).collect::<Vec<&str>>();
+            long_say!(ctx, msg, to_send, ", ")
+        }
+        None => reply!(ctx, msg, "nothing matched your search", false),
+    }
+    Ok(())
+}
+
+#[command("top")]
+/// get the chips which deal the most damage, counting every hit they can make
+/// optionally give an element, how many chips to show (10 by default), and whether to sort by avg or max damage
+#[example = "fire 10"]
+#[example = "aqua max"]
+#[example = "5"]
+async fn top_chips(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut element = None;
+    let mut count = 10;
+    let mut by_max = false;
+    for arg in args.raw() {
+        if let Ok(num) = arg.parse::<usize>() {
+            count = num.clamp(1, 25);
+        } else if arg.eq_ignore_ascii_case("max") {
+            by_max = true;
+        } else if arg.eq_ignore_ascii_case("avg") || arg.eq_ignore_ascii_case("average") {
+            by_max = false;
+        } else if let Ok(elem) = Elements::from_str(arg) {
+            element = Some(elem);
+        } else if !arg.eq_ignore_ascii_case("any") {
+            reply!(ctx, msg, format!("{} is not an element, a number, avg, or max", arg), false);
+            return Ok(());
+        }
+    }
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+
+    let mut chips = library
+        .chips
+        .values()
+        .filter(|chip| chip.damage.is_some() && chip.hits.is_some())
+        .filter(|chip| element.is_none_or(|elem| chip.element.contains(&elem)))
+        .collect::<Vec<&Arc<BattleChip>>>();
+
+    let key = |chip: &BattleChip| {
+        if by_max {
+            chip.max_damage().unwrap_or_default() as f64
+        } else {
+            chip.average_damage().unwrap_or_default()
+        }
+    };
+    chips.sort_by(|a, b| key(b).total_cmp(&key(a)).then_with(|| a.name.cmp(&b.name)));
+
+    if chips.is_empty() {
+        reply!(ctx, msg, "No chips deal damage of that element", false);
+        return Ok(());
+    }
+
+    let to_send = chips
+        .iter()
+        .take(count)
+        .enumerate()
+        .map(|(index, chip)| {
+            let damage = chip.damage.map(|d| d.to_string()).unwrap_or_default();
+            let hits = chip.hits.map_or(1, |h| h.max);
+            let times = if hits > 1 { format!(" x{}", hits) } else { String::new() };
+            format!(
+                "{}. {} - {}{} (avg {:.1}, max {})",
+                index + 1,
+                chip.name,
+                damage,
+                times,
+                chip.average_damage().unwrap_or_default(),
+                chip.max_damage().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<String>>();
+
+    long_say!(ctx, msg, to_send, "\n");
+    Ok(())
+}
+
+/// Shared by the class, type, and range commands, the first argument is what to filter on
+/// and an element may optionally follow it
+async fn send_chip_filter<T, F>(ctx: &Context, msg: &Message, mut args: Args, what: &str, search: F) -> CommandResult
+where
+    T: FromStr,
+    F: for<'a> Fn(&'a ChipLibrary, T, Option<Elements>) -> Option<Vec<&'a Arc<BattleChip>>>,
+{
+    let filter = match args.single::<String>() {
+        Ok(filter) => filter,
+        Err(_) => {
+            reply!(ctx, msg, format!("you must provide a {}", what));
+            return Ok(());
+        }
+    };
+
+    let filter = match T::from_str(&filter) {
+        Ok(filter) => filter,
+        Err(_) => {
+            reply!(ctx, msg, format!("{} is not a {}, perhaps you spelled it wrong?", filter, what), false);
+            return Ok(());
+        }
+    };
+
+    let element = if args.is_empty() {
+        None
+    } else {
+        match Elements::from_str(args.rest().trim()) {
+            Ok(element) => Some(element),
+            Err(_) => {
+                reply!(ctx, msg, "That could not be parsed as an element, perhaps you spelled it wrong?", false);
+                return Ok(());
+            }
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+
+    match search(&library, filter, element) {
+        Some(chips) => {
+            let to_send = chips.iter().map(|a| a.get_name()).collect::<Vec<&str>>();
+            long_say!(ctx, msg, to_send, ", ")
+        }
+        None => reply!(ctx, msg, "nothing matched your search", false),
+    }
+    Ok(())
+}
+
+#[command("class")]
+/// get a list of chips of the specified class, optionally only those of an element as well
+/// the classes are Standard, Mega, Giga, Dark, and Support
+#[example = "Giga"]
+#[example = "Mega Fire"]
+async fn send_chip_class(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    send_chip_filter::<ChipClass, _>(ctx, msg, args, "chip class", ChipLibrary::search_class).await
+}
+
+#[command("type")]
+/// get a list of chips of the specified type, optionally only those of an element as well
+/// the types are Burst, Construct, Melee, Projectile, Wave, Recovery, Summon, Support, and Trap
+#[example = "Wave"]
+#[example = "Melee Sword"]
+async fn send_chip_type(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    send_chip_filter::<ChipType, _>(ctx, msg, args, "chip type", ChipLibrary::search_type).await
+}
+
+#[command("range")]
+/// get a list of chips with the specified range, optionally only those of an element as well
+/// the ranges are Self, Close, Near, Far, and Varies
+#[example = "Far"]
+#[example = "Near Elec"]
+async fn send_chip_range(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    send_chip_filter::<Ranges, _>(ctx, msg, args, "range", ChipLibrary::search_range).await
+}
+
+const COMPARE_FIELDS: [&str; 9] = [
+    "Element", "Skills", "Range", "Damage", "Hits", "Class", "Type", "Blight", "Save",
+];
+
+/// The most chips compared in a single table, any more are listed one after another instead
+const MAX_COMPARE_COLUMNS: usize = 4;
+
+/// Each of the compared fields of a chip, in the same order as `COMPARE_FIELDS`
+fn compare_values(chip: &BattleChip) -> [String; 9] {
+    let save = match (chip.skill_target, chip.skill_user) {
+        (Skills::None, Skills::None) => String::from("--"),
+        (target, user) => format!("{} vs DC+{}", target.abbreviation(), user.abbreviation()),
+    };
+
+    [
+        chip.element.iter().format(", ").to_string(),
+        chip.skills.iter().map(|s| s.abbreviation()).format(", ").to_string(),
+        chip.range.to_string(),
+        chip.damage.map_or_else(|| String::from("--"), |d| d.to_string()),
+        chip.hits.map_or_else(|| String::from("--"), |h| h.to_string()),
+        if chip.class == ChipClass::Standard { String::from("Standard") } else { chip.class.to_string() },
+        chip.kind.to_string(),
+        chip.blight.map_or_else(|| String::from("--"), |b| b.to_string()),
+        save,
+    ]
+}
+
+/// Lays the chips out as columns of a table, rows which differ start with a + to be highlighted
+fn compare_table(names: &[&str], values: &[[String; 9]], differs: &[bool]) -> String {
+    let label_width = COMPARE_FIELDS.iter().map(|f| f.len()).max().unwrap_or(0);
+    let widths = names
+        .iter()
+        .zip(values)
+        .map(|(name, vals)| vals.iter().map(String::len).chain(std::iter::once(name.len())).max().unwrap_or(0))
+        .collect::<Vec<usize>>();
+
+    let mut table = format!("```diff\n  {:<width$}", "", width = label_width);
+    for (name, width) in names.iter().zip(&widths) {
+        table.push_str(&format!(" | {:<width$}", name, width = width));
+    }
+
+    for (row, field) in COMPARE_FIELDS.iter().enumerate() {
+        let marker = if differs[row] { '+' } else { ' ' };
+        table.push_str(&format!("\n{} {:<width$}", marker, field, width = label_width));
+        for (vals, width) in values.iter().zip(&widths) {
+            table.push_str(&format!(" | {:<width$}", vals[row], width = width));
+        }
+    }
+    table.push_str("\n```");
+    table
+}
+
+#[command("compare")]
+/// compare two or more chips side by side, fields that differ between them are highlighted
+/// separate chip names with commas if any of them contain spaces
+#[example = "Cannon HiCannon"]
+#[example = "Airshot, Vulcan, Spreader"]
+async fn compare_chips(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let rest = args.rest();
+    let to_get = if rest.contains(',') {
+        rest.split(',').map(str::trim).filter(|n| !n.is_empty()).collect::<Vec<&str>>()
+    } else {
+        rest.split_whitespace().collect::<Vec<&str>>()
+    };
+
+    if to_get.len() < 2 {
+        reply!(ctx, msg, "you must provide at least two chips to compare");
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let homebrew = homebrew_lock.read().await;
+
+    let mut chips = Vec::with_capacity(to_get.len());
+    for name in &to_get {
+        match homebrew.search(msg.guild_id, &library, name) {
+            Ok(chip) => chips.push(chip),
+            Err(suggestions) => {
+                let to_say = suggestions.iter().map(|a| a.get_name()).collect::<Vec<&str>>().join(", ");
+                reply!(ctx, msg, format!("Could not find {}, did you mean: {}", name, to_say));
+                return Ok(());
+            }
+        }
+    }
+
+    let names = chips.iter().map(|c| c.get_name()).collect::<Vec<&str>>();
+    let values = chips.iter().map(|c| compare_values(c)).collect::<Vec<[String; 9]>>();
+    let differs = (0..COMPARE_FIELDS.len())
+        .map(|row| values.iter().any(|vals| vals[row] != values[0][row]))
+        .collect::<Vec<bool>>();
+
+    if chips.len() <= MAX_COMPARE_COLUMNS {
+        let table = compare_table(&names, &values, &differs);
+        if table.len() <= 1900 {
+            reply!(ctx, msg, table);
+            return Ok(());
+        }
+    }
+
+    // too wide for a table, so list each chip on its own with the differing fields in bold
+    let to_send = names
+        .iter()
+        .zip(&values)
+        .map(|(name, vals)| {
+            let fields = COMPARE_FIELDS
+                .iter()
+                .zip(vals.iter())
+                .zip(&differs)
+                .map(|((field, val), differs)| {
+                    if *differs {
+                        format!("**{}: {}**", field, val)
+                    } else {
+                        format!("{}: {}", field, val)
+                    }
+                })
+                .join(" | ");
+            format!("__{}__\n{}", name, fields)
+        })
+        .collect::<Vec<String>>();
+
+    long_say!(ctx, msg, to_send, "\n");
+    Ok(())
+}
+
 #[command("blight")]
 /// get a list of chips which can cause a blight of that element
 #[example = "Sword"]
@@ -493,4 +1124,73 @@ async fn chip_drop_cr(ctx: &Context, msg: &Message, mut args: Args) -> CommandRe
     long_say!(ctx, msg, drop_list, ", ");
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The names of the chips parsed, and the line, field, and raw text of each diagnostic
+    fn parse(text: &str) -> (Vec<String>, Vec<(usize, &'static str, String)>) {
+        let (chips, diagnostics) = parse_chip_text(text, "test");
+        (
+            chips.into_iter().map(|chip| chip.name).collect(),
+            diagnostics.into_iter().map(|diag| (diag.line, diag.error.field, diag.raw)).collect(),
+        )
+    }
+
+    #[test]
+    fn parses_good_chips() {
+        let (chips, diagnostics) = parse(crate::library::test_chips::TEST_CHIPS);
+        assert_eq!(chips, vec!["Cannon", "AirShot", "FireSword", "AquaTower", "Recover", "WideSword"]);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn skips_a_stray_line() {
+        let (chips, diagnostics) = parse(
+            "Cannon - Null | Per | Far | 2d6 damage | | 1 hit | Projectile\n\
+             A simple cannon shot.\n\
+             \n\
+             This line was left behind\n\
+             FireSword - Fire | Str | Close | 3d6 damage | | 1 hit | Melee\n\
+             A burning slash.\n",
+        );
+        assert_eq!(chips, vec!["Cannon", "FireSword"]);
+        assert_eq!(diagnostics, vec![(4, "name", String::from("This line was left behind"))]);
+    }
+
+    #[test]
+    fn reports_a_missing_description() {
+        let (chips, diagnostics) = parse(
+            "Cannon - Null | Per | Far | 2d6 damage | | 1 hit | Projectile\n\
+             FireSword - Fire | Str | Close | 3d6 damage | | 1 hit | Melee\n\
+             A burning slash.\n\
+             Recover - Null | None | Self | -- | | -- hits | Recovery\n",
+        );
+        assert_eq!(chips, vec!["FireSword"]);
+        assert_eq!(
+            diagnostics.iter().map(|(line, field, _)| (*line, *field)).collect::<Vec<_>>(),
+            vec![(1, "description"), (4, "description")]
+        );
+    }
+
+    #[test]
+    fn reports_the_group_which_failed() {
+        let (chips, diagnostics) = parse(
+            "Cannon - Null | Per | Far | 2d6 damage | | one hit | Projectile\n\
+             A simple cannon shot.\n\
+             AirShot - Wind | Per | Nowhere | 1d6 damage | | 1 hit | Projectile\n\
+             A puff of air.\n\
+             AquaTower - Aqua | Per | Near | 2d6 damage | Mega | 1-3 hits | Wave\n\
+             A pillar of water.\n\
+             FireSword - Fire | Str | Close | 3x6 damage | | 1 hit | Melee\n\
+             A burning slash.\n",
+        );
+        assert_eq!(chips, vec!["AquaTower"]);
+        assert_eq!(
+            diagnostics.iter().map(|(line, field, _)| (*line, *field)).collect::<Vec<_>>(),
+            vec![(1, "hits"), (3, "range"), (7, "damage")]
+        );
+    }
+}
@@ -59,3 +59,23 @@ impl std::fmt::Display for Elements {
         }
     }
 }
+
+impl Elements {
+    /// The colour used for embeds of chips and viruses with this element
+    pub fn colour(self) -> u32 {
+        match self {
+            Elements::Fire => 0xE74C3C,
+            Elements::Aqua => 0x3498DB,
+            Elements::Elec => 0xF1C40F,
+            Elements::Wood => 0x2ECC71,
+            Elements::Wind => 0x76D7C4,
+            Elements::Sword => 0x95A5A6,
+            Elements::Break => 0xE67E22,
+            Elements::Cursor => 0x9B59B6,
+            Elements::Recovery => 0xF78FB3,
+            Elements::Invis => 0x34495E,
+            Elements::Object => 0x8D6E63,
+            Elements::Null => 0xECF0F1,
+        }
+    }
+}
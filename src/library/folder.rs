@@ -0,0 +1,808 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::{channel::Message, id::UserId},
+    prelude::*,
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use simple_error::SimpleError;
+
+use crate::{
+    bot_data::{BotData, FolderRules},
+    library::{
+        battlechip::{chip_type::ChipClass, BattleChip},
+        chip_library::ChipLibrary,
+        Library,
+    },
+    LibraryObject,
+};
+
+const FOLDER_FILE: &str = "./folders.json";
+
+/// The most folders a single user may save
+const MAX_FOLDERS: usize = 10;
+
+const MAX_NAME_LEN: usize = 32;
+
+/// How many chips are drawn for each slot of a random folder before giving up on filling it
+const RANDOM_DRAWS_PER_CHIP: usize = 20;
+
+/// The largest manager export which will be read from an attachment, in bytes
+const MAX_IMPORT_SIZE: u64 = 64 * 1024;
+
+/// A single chip in an exported folder
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ManagerChip {
+    #[serde(alias = "name", alias = "Chip", alias = "chip")]
+    name: String,
+    #[serde(default, alias = "used")]
+    used: bool,
+}
+
+/// A folder in the shape the bot exports it, one entry per copy of a chip
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ManagerFolder {
+    folder: Vec<ManagerChip>,
+}
+
+/// A chip in an imported folder, either just its name or an object naming it
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ImportedChip {
+    Name(String),
+    Chip(ManagerChip),
+}
+
+impl ImportedChip {
+    fn name(&self) -> &str {
+        match self {
+            ImportedChip::Name(name) => name,
+            ImportedChip::Chip(chip) => &chip.name,
+        }
+    }
+}
+
+/// A folder to import. The chip manager does not publish its export format, so rather than
+/// trusting one guessed shape this reads a bare list of chips, or an object holding that list
+/// under Folder or Chips, which covers the bot's own exports. Anything else in the object is ignored.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FolderImport {
+    List(Vec<ImportedChip>),
+    Folder {
+        #[serde(rename = "Folder", alias = "folder", alias = "Chips", alias = "chips")]
+        folder: Vec<ImportedChip>,
+    },
+}
+
+impl FolderImport {
+    fn into_chips(self) -> Vec<ImportedChip> {
+        match self {
+            FolderImport::List(chips) | FolderImport::Folder { folder: chips } => chips,
+        }
+    }
+}
+
+/// A saved folder, the chips in it and how many copies of each
+#[derive(Serialize, Deserialize, Default)]
+pub struct ChipFolder {
+    chips: BTreeMap<String, usize>,
+    /// Chips which were no longer in the library at the last reload
+    #[serde(default)]
+    flagged: BTreeSet<String>,
+}
+
+impl ChipFolder {
+    pub(crate) fn size(&self) -> usize {
+        self.chips.values().sum()
+    }
+
+    /// Checks that adding copies of a chip keeps this folder within the rules
+    fn check_add(
+        &self,
+        chip: &BattleChip,
+        count: usize,
+        rules: &FolderRules,
+        library: &ChipLibrary,
+    ) -> Result<(), SimpleError> {
+        let size = self.size() + count;
+        if size > rules.max_size {
+            return Err(SimpleError::new(format!(
+                "That would make the folder {} chips, but folders can only hold {}",
+                size, rules.max_size
+            )));
+        }
+
+        let copies = self.chips.get(&chip.name).copied().unwrap_or(0) + count;
+        if copies > rules.max_copies {
+            return Err(SimpleError::new(format!(
+                "That would make {} copies of {}, but folders can only hold {} of each chip",
+                copies, chip.name, rules.max_copies
+            )));
+        }
+
+        let cap = match chip.class {
+            ChipClass::Mega => rules.max_mega,
+            ChipClass::Giga => rules.max_giga,
+            ChipClass::Dark => rules.max_dark,
+            ChipClass::Standard | ChipClass::Support => return Ok(()),
+        };
+
+        let in_class = self
+            .chips
+            .iter()
+            .filter(|(name, _)| library.get(name).is_some_and(|other| other.class == chip.class))
+            .map(|(_, count)| count)
+            .sum::<usize>()
+            + count;
+        if in_class > cap {
+            return Err(SimpleError::new(format!(
+                "That would make {} {} chips, but folders can only hold {}",
+                in_class, chip.class, cap
+            )));
+        }
+        Ok(())
+    }
+
+    /// Builds a folder from an imported list of chips, returning it with the names of any unknown chips
+    fn from_manager(
+        export: FolderImport,
+        rules: &FolderRules,
+        library: &ChipLibrary,
+    ) -> Result<(ChipFolder, Vec<String>), SimpleError> {
+        let mut folder = ChipFolder::default();
+        let mut unknown = vec![];
+        for entry in export.into_chips() {
+            match library.get(entry.name().trim()) {
+                Some(chip) => {
+                    folder.check_add(chip, 1, rules, library)?;
+                    *folder.chips.entry(chip.name.clone()).or_default() += 1;
+                }
+                None => unknown.push(entry.name().trim().to_owned()),
+            }
+        }
+        unknown.sort_unstable();
+        unknown.dedup();
+        Ok((folder, unknown))
+    }
+
+    /// The folder as JSON for exporting, one entry per copy
+    fn to_manager(&self) -> ManagerFolder {
+        ManagerFolder {
+            folder: self
+                .chips
+                .iter()
+                .flat_map(|(name, count)| {
+                    std::iter::repeat_with(move || ManagerChip { name: name.clone(), used: false }).take(*count)
+                })
+                .collect(),
+        }
+    }
+
+    /// Builds a random folder of up to the given size from a set of chips, following the rules
+    pub(crate) fn random<R: Rng>(
+        chips: &[&Arc<BattleChip>],
+        size: usize,
+        rules: &FolderRules,
+        library: &ChipLibrary,
+        rng: &mut R,
+    ) -> Result<ChipFolder, SimpleError> {
+        if size > rules.max_size {
+            return Err(SimpleError::new(format!("Folders can only hold {} chips", rules.max_size)));
+        }
+        if chips.is_empty() {
+            return Err(SimpleError::new("There are no chips to build a folder from"));
+        }
+
+        let mut folder = ChipFolder::default();
+        for _ in 0..size * RANDOM_DRAWS_PER_CHIP {
+            if folder.size() >= size {
+                break;
+            }
+            let chip = chips[rng.gen_range(0..chips.len())];
+            if folder.check_add(chip, 1, rules, library).is_ok() {
+                *folder.chips.entry(chip.name.clone()).or_default() += 1;
+            }
+        }
+        Ok(folder)
+    }
+
+    /// Flags every chip which is not in the library, returns true if any are flagged
+    fn flag_missing(&mut self, library: &ChipLibrary) -> bool {
+        self.flagged = self
+            .chips
+            .keys()
+            .filter(|name| library.get(name).is_none())
+            .cloned()
+            .collect();
+        !self.flagged.is_empty()
+    }
+}
+
+impl std::fmt::Display for ChipFolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} chips", self.size())?;
+        for (name, count) in &self.chips {
+            write!(f, "\n{} x{}", name, count)?;
+            if self.flagged.contains(name) {
+                write!(f, " (no longer in the library)")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Chip folders saved by each user
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Folders(HashMap<u64, BTreeMap<String, ChipFolder>>);
+
+impl Folders {
+    /// Loads the saved folders, starting fresh if there are none yet
+    pub async fn import() -> RwLock<Folders> {
+        let folders = match tokio::fs::read_to_string(FOLDER_FILE).await {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|why| {
+                eprintln!("Could not parse {}, starting with no folders: {}", FOLDER_FILE, why);
+                Folders::default()
+            }),
+            Err(_) => Folders::default(),
+        };
+        RwLock::new(folders)
+    }
+
+    pub async fn save(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string(&self.0)?;
+        tokio::fs::write(FOLDER_FILE, json).await?;
+        Ok(())
+    }
+
+    fn get(&self, user: UserId, name: &str) -> Result<&ChipFolder, SimpleError> {
+        self.0
+            .get(&user.0)
+            .and_then(|folders| folders.get(&name.to_lowercase()))
+            .ok_or_else(|| SimpleError::new(format!("You have no folder named {}", name)))
+    }
+
+    fn get_mut(&mut self, user: UserId, name: &str) -> Result<&mut ChipFolder, SimpleError> {
+        self.0
+            .get_mut(&user.0)
+            .and_then(|folders| folders.get_mut(&name.to_lowercase()))
+            .ok_or_else(|| SimpleError::new(format!("You have no folder named {}", name)))
+    }
+
+    fn create(&mut self, user: UserId, name: &str) -> Result<(), SimpleError> {
+        if name.len() > MAX_NAME_LEN {
+            return Err(SimpleError::new(format!(
+                "Folder names can be at most {} characters long",
+                MAX_NAME_LEN
+            )));
+        }
+
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(SimpleError::new(
+                "Folder names can only contain letters, numbers, - and _",
+            ));
+        }
+
+        let folders = self.0.entry(user.0).or_default();
+        let name = name.to_lowercase();
+        if folders.contains_key(&name) {
+            return Err(SimpleError::new(format!("You already have a folder named {}", name)));
+        }
+        if folders.len() >= MAX_FOLDERS {
+            return Err(SimpleError::new(format!(
+                "You can only have {} folders, delete one first",
+                MAX_FOLDERS
+            )));
+        }
+
+        folders.insert(name, ChipFolder::default());
+        Ok(())
+    }
+
+    fn insert(&mut self, user: UserId, name: &str, folder: ChipFolder) -> Result<(), SimpleError> {
+        self.create(user, name)?;
+        *self.get_mut(user, name)? = folder;
+        Ok(())
+    }
+
+    fn delete(&mut self, user: UserId, name: &str) -> Option<ChipFolder> {
+        let folders = self.0.get_mut(&user.0)?;
+        let removed = folders.remove(&name.to_lowercase());
+        if folders.is_empty() {
+            self.0.remove(&user.0);
+        }
+        removed
+    }
+
+    /// Flags chips missing from the library in every folder, returns how many folders have any
+    pub fn flag_missing(&mut self, library: &ChipLibrary) -> usize {
+        self.0
+            .values_mut()
+            .flat_map(|folders| folders.values_mut())
+            .map(|folder| folder.flag_missing(library))
+            .filter(|flagged| *flagged)
+            .count()
+    }
+}
+
+impl TypeMapKey for Folders {
+    type Value = RwLock<Folders>;
+}
+
+/// Splits "folder chip name [count]" into its parts
+fn parse_entry(args: &Args) -> Result<(&str, &str, Option<usize>), SimpleError> {
+    let (folder, rest) = args
+        .rest()
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| SimpleError::new("You must supply a folder name and a chip"))?;
+    let rest = rest.trim();
+
+    match rest.rsplit_once(char::is_whitespace) {
+        Some((chip, count)) => match count.parse::<usize>() {
+            Ok(0) => Err(SimpleError::new("You must supply at least 1 copy")),
+            Ok(count) => Ok((folder, chip.trim(), Some(count))),
+            Err(_) => Ok((folder, rest, None)),
+        },
+        None => Ok((folder, rest, None)),
+    }
+}
+
+#[group]
+#[prefixes("folder", "folders")]
+#[default_command(show_folder)]
+#[commands(
+    create_folder,
+    delete_folder,
+    add_to_folder,
+    remove_from_folder,
+    show_folder,
+    export_folder,
+    import_folder
+)]
+/// A group of commands for building and saving chip folders
+struct Folder;
+
+#[command("create")]
+#[aliases("new")]
+/// Create a new empty folder
+#[example = "main"]
+async fn create_folder(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must supply a name for the folder");
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let mut folders = folders_lock.write().await;
+
+    let name = args.rest().trim();
+    if let Err(why) = folders.create(msg.author.id, name) {
+        reply!(ctx, msg, why);
+        return Ok(());
+    }
+
+    if let Err(why) = folders.save().await {
+        eprintln!("Could not save folders: {:?}", why);
+        reply!(ctx, msg, "Folder created, but it could not be saved and will be lost on a restart");
+        return Ok(());
+    }
+
+    reply!(ctx, msg, format!("Created folder {}", name.to_lowercase()));
+    Ok(())
+}
+
+#[command("delete")]
+#[aliases("del")]
+/// Delete one of your folders
+#[example = "main"]
+async fn delete_folder(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must supply the name of the folder to delete");
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let mut folders = folders_lock.write().await;
+
+    let name = args.rest().trim();
+    if folders.delete(msg.author.id, name).is_none() {
+        reply!(ctx, msg, format!("You have no folder named {}", name));
+        return Ok(());
+    }
+
+    if let Err(why) = folders.save().await {
+        eprintln!("Could not save folders: {:?}", why);
+    }
+
+    reply!(ctx, msg, format!("Deleted folder {}", name.to_lowercase()));
+    Ok(())
+}
+
+#[command("add")]
+/// Add copies of a chip to one of your folders, checking it against the folder rules
+/// Folders are kept across servers, so only official chips can be added, not a server's homebrew
+#[example = "main Cannon 2"]
+async fn add_to_folder(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (name, to_get, count) = match parse_entry(&args) {
+        Ok(entry) => entry,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+    let count = count.unwrap_or(1);
+
+    let data = ctx.data.read().await;
+    let rules = &data.get::<BotData>().expect("no bot data available").folder_rules;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let mut folders = folders_lock.write().await;
+
+    let chip = match library.search_lib_obj(to_get) {
+        Ok(chip) => chip,
+        Err(chips) => {
+            let to_say = chips.iter().map(|a| a.get_name()).collect::<Vec<&str>>().join(", ");
+            reply!(ctx, msg, format!("Could not find {}, did you mean: {}", to_get, to_say));
+            return Ok(());
+        }
+    };
+
+    let folder = match folders.get_mut(msg.author.id, name) {
+        Ok(folder) => folder,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    if let Err(why) = folder.check_add(chip, count, rules, &library) {
+        reply!(ctx, msg, why);
+        return Ok(());
+    }
+
+    *folder.chips.entry(chip.name.clone()).or_default() += count;
+    let size = folder.size();
+
+    if let Err(why) = folders.save().await {
+        eprintln!("Could not save folders: {:?}", why);
+        reply!(ctx, msg, "Chip added, but the folder could not be saved and will be lost on a restart");
+        return Ok(());
+    }
+
+    reply!(
+        ctx,
+        msg,
+        format!("Added {} x{} to {}, it now holds {} chips", chip.name, count, name.to_lowercase(), size)
+    );
+    Ok(())
+}
+
+#[command("remove")]
+#[aliases("rem")]
+/// Remove copies of a chip from one of your folders, all of them if no number is given
+#[example = "main Cannon 1"]
+async fn remove_from_folder(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (name, to_get, count) = match parse_entry(&args) {
+        Ok(entry) => entry,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let mut folders = folders_lock.write().await;
+
+    let folder = match folders.get_mut(msg.author.id, name) {
+        Ok(folder) => folder,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    // chips no longer in the library can still be removed, so match on the saved name
+    let chip_name = match folder.chips.keys().find(|chip| chip.eq_ignore_ascii_case(to_get)) {
+        Some(chip_name) => chip_name.clone(),
+        None => {
+            reply!(ctx, msg, format!("{} is not in {}", to_get, name.to_lowercase()));
+            return Ok(());
+        }
+    };
+
+    let held = folder.chips[&chip_name];
+    if count.is_none_or(|count| count >= held) {
+        folder.chips.remove(&chip_name);
+        folder.flagged.remove(&chip_name);
+    } else {
+        folder.chips.insert(chip_name.clone(), held - count.unwrap_or(held));
+    }
+    let size = folder.size();
+
+    if let Err(why) = folders.save().await {
+        eprintln!("Could not save folders: {:?}", why);
+    }
+
+    reply!(
+        ctx,
+        msg,
+        format!("Removed {} from {}, it now holds {} chips", chip_name, name.to_lowercase(), size)
+    );
+    Ok(())
+}
+
+#[command("show")]
+#[aliases("list")]
+/// List your folders, or show the chips in one of them
+#[example = "main"]
+async fn show_folder(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let folders = folders_lock.read().await;
+
+    if args.is_empty() {
+        match folders.0.get(&msg.author.id.0).filter(|list| !list.is_empty()) {
+            Some(list) => {
+                let to_send = list
+                    .iter()
+                    .map(|(name, folder)| {
+                        let warning = if folder.flagged.is_empty() { "" } else { ", has missing chips" };
+                        format!("{}: {} chips{}", name, folder.size(), warning)
+                    })
+                    .collect::<Vec<String>>();
+                long_say!(ctx, msg, to_send, "\n");
+            }
+            None => reply!(ctx, msg, "You have no saved folders"),
+        }
+        return Ok(());
+    }
+
+    let name = args.rest().trim();
+    match folders.get(msg.author.id, name) {
+        Ok(folder) => {
+            let mut to_send = format!("**{}**: {}", name.to_lowercase(), folder);
+            if !folder.flagged.is_empty() {
+                to_send.push_str("\nSome chips are no longer in the library, remove or replace them");
+            }
+            say!(ctx, msg, to_send);
+        }
+        Err(why) => reply!(ctx, msg, why),
+    }
+    Ok(())
+}
+
+#[command("export")]
+/// Get one of your folders as a plain list which can be copied elsewhere
+/// Add json to get the folder as a JSON file, which the import command reads back
+#[example = "main"]
+#[example = "main json"]
+async fn export_folder(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must supply the name of the folder to export");
+        return Ok(());
+    }
+
+    let name = args.single::<String>()?;
+    let as_json = match args.rest().trim().to_lowercase().as_str() {
+        "" => false,
+        "json" | "manager" => true,
+        other => {
+            reply!(ctx, msg, format!("{} is not an export format, try json", other));
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let folders = folders_lock.read().await;
+
+    let folder = match folders.get(msg.author.id, &name) {
+        Ok(folder) => folder,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    if !as_json {
+        let list = folder
+            .chips
+            .iter()
+            .map(|(chip, count)| format!("{} x{}", chip, count))
+            .collect::<Vec<String>>()
+            .join("\n");
+        say!(ctx, msg, format!("```\n{}\n```", list));
+        return Ok(());
+    }
+
+    let json = serde_json::to_string_pretty(&folder.to_manager())?;
+    let file_name = format!("{}.json", name.to_lowercase());
+    if let Err(why) = msg
+        .channel_id
+        .send_files(&ctx.http, vec![(json.as_bytes(), file_name.as_str())], |m| {
+            m.content(format!("{} as JSON", name.to_lowercase()))
+        })
+        .await
+    {
+        eprintln!("Could not send folder export: {:?}", why);
+        reply!(ctx, msg, "Could not send the exported folder, inform Major");
+    }
+    Ok(())
+}
+
+/// Removes a code block around pasted JSON, if there is one
+fn strip_code_block(text: &str) -> &str {
+    let text = text.trim();
+    match text.strip_prefix("```") {
+        Some(inner) => inner.trim_start_matches("json").trim_end_matches("```").trim(),
+        None => text,
+    }
+}
+
+#[command("import")]
+/// Import a folder from a JSON export, such as one made by the export command, as a new folder
+/// Attach the exported file, or paste its contents after the folder name
+/// A list of chip names, or of objects with a Name, is also accepted
+/// Chips which are not in the library are skipped and listed
+#[example = "main"]
+#[example = "main {\"Folder\": [{\"Name\": \"Cannon\", \"Used\": false}]}"]
+#[example = "main [\"Cannon\", \"Cannon\", \"AirShot\"]"]
+async fn import_folder(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must supply a name for the imported folder");
+        return Ok(());
+    }
+
+    let name = args.single::<String>()?;
+    let text = match msg.attachments.first() {
+        Some(attachment) if attachment.size > MAX_IMPORT_SIZE => {
+            reply!(ctx, msg, "That file is too large to be a folder export");
+            return Ok(());
+        }
+        Some(attachment) => match attachment.download().await.map(String::from_utf8) {
+            Ok(Ok(text)) => text,
+            Ok(Err(_)) => {
+                reply!(ctx, msg, "That file is not a folder export");
+                return Ok(());
+            }
+            Err(why) => {
+                eprintln!("Could not download folder export: {:?}", why);
+                reply!(ctx, msg, "Could not download that file, try pasting its contents instead");
+                return Ok(());
+            }
+        },
+        None => strip_code_block(args.rest()).to_owned(),
+    };
+
+    if text.is_empty() {
+        reply!(ctx, msg, "You must attach or paste a folder export");
+        return Ok(());
+    }
+
+    let export = match serde_json::from_str::<FolderImport>(&text) {
+        Ok(export) => export,
+        Err(_) => {
+            reply!(ctx, msg, "That is not a folder export, it must be a list of chips or hold one under \"Folder\"");
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let rules = &data.get::<BotData>().expect("no bot data available").folder_rules;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let mut folders = folders_lock.write().await;
+
+    let (folder, unknown) = match ChipFolder::from_manager(export, rules, &library) {
+        Ok(imported) => imported,
+        Err(why) => {
+            reply!(ctx, msg, format!("Could not import that folder: {}", why));
+            return Ok(());
+        }
+    };
+
+    let size = folder.size();
+    if let Err(why) = folders.insert(msg.author.id, &name, folder) {
+        reply!(ctx, msg, why);
+        return Ok(());
+    }
+
+    if let Err(why) = folders.save().await {
+        eprintln!("Could not save folders: {:?}", why);
+        reply!(ctx, msg, "Folder imported, but it could not be saved and will be lost on a restart");
+        return Ok(());
+    }
+
+    let mut to_send = format!("Imported {} with {} chips", name.to_lowercase(), size);
+    if !unknown.is_empty() {
+        to_send.push_str(&format!(
+            "\nThese chips are not in the library and were skipped: {}",
+            unknown.join(", ")
+        ));
+    }
+    reply!(ctx, msg, to_send);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::test_chips;
+
+    /// A hand written folder in the shape the bot exports, not one captured from the chip manager
+    const EXAMPLE_EXPORT: &str = include_str!("../../exampleManagerFolder.json");
+
+    #[test]
+    fn reads_the_example_export() {
+        let export: FolderImport = serde_json::from_str(EXAMPLE_EXPORT).expect("example export did not parse");
+        let chips = export.into_chips();
+        assert_eq!(chips.len(), 6);
+        assert_eq!(chips[0].name(), "Cannon");
+        assert!(matches!(&chips[2], ImportedChip::Chip(chip) if chip.used));
+    }
+
+    #[test]
+    fn reads_lowercase_keys() {
+        let export: FolderImport =
+            serde_json::from_str(r#"{"folder": [{"name": "Cannon"}, {"name": "AirShot", "used": true}]}"#)
+                .expect("lowercase export did not parse");
+        let chips = export.into_chips();
+        assert_eq!(chips.len(), 2);
+        assert!(matches!(&chips[1], ImportedChip::Chip(chip) if chip.used));
+    }
+
+    #[test]
+    fn reads_a_bare_list_of_chips() {
+        let export: FolderImport =
+            serde_json::from_str(r#"["Cannon", {"Name": "AirShot"}, {"chip": "Recover"}]"#).expect("list did not parse");
+        let names = export.into_chips().iter().map(|chip| chip.name().to_owned()).collect::<Vec<String>>();
+        assert_eq!(names, vec!["Cannon", "AirShot", "Recover"]);
+
+        let export: FolderImport = serde_json::from_str(r#"{"Chips": ["Cannon"]}"#).expect("chips did not parse");
+        assert_eq!(export.into_chips().len(), 1);
+
+        assert!(serde_json::from_str::<FolderImport>(r#"{"Limit": 12}"#).is_err());
+    }
+
+    #[test]
+    fn round_trips_the_example_export() {
+        let library = test_chips::library();
+                let rules = FolderRules::default();
+
+        let export: FolderImport = serde_json::from_str(EXAMPLE_EXPORT).unwrap();
+        let (folder, unknown) = ChipFolder::from_manager(export, &rules, &library).expect("example export was refused");
+        assert_eq!(unknown, vec![String::from("NotARealChip")]);
+        assert_eq!(folder.size(), 5);
+        assert_eq!(folder.chips.get("Cannon"), Some(&2));
+        assert_eq!(folder.chips.get("AirShot"), Some(&1));
+
+        let json = serde_json::to_string(&folder.to_manager()).unwrap();
+        let reimported: FolderImport = serde_json::from_str(&json).unwrap();
+        let (again, unknown) = ChipFolder::from_manager(reimported, &rules, &library).unwrap();
+        assert!(unknown.is_empty());
+        assert_eq!(again.chips, folder.chips);
+    }
+
+    #[test]
+    fn refuses_too_many_copies() {
+        let library = test_chips::library();
+        let rules = FolderRules { max_copies: 1, ..FolderRules::default() };
+        let export: FolderImport = serde_json::from_str(EXAMPLE_EXPORT).unwrap();
+        assert!(ChipFolder::from_manager(export, &rules, &library).is_err());
+    }
+}
@@ -3,8 +3,8 @@ use ncp_library::NCPLibrary;
 use tokio::sync::{RwLock, RwLockReadGuard};
 
 use crate::{
-    library::{Library, LibraryObject},
-    util::{edit_message_by_id, has_reaction_perm, reaction_did_you_mean, send_reply},
+    library::{homebrew::reply_homebrew, render::Renderer, Library, LibraryObject},
+    util::{has_reaction_perm, reaction_did_you_mean, send_reply},
     ChipLibrary, VirusLibrary,
 };
 
@@ -140,20 +140,24 @@ pub(crate) async fn search_full_library(ctx: &Context, msg: &Message, args: &[&s
     let library_lock = data.get::<FullLibrary>().expect("Full library not found");
     let library: RwLockReadGuard<FullLibrary> = library_lock.read().await;
 
+    let renderer = Renderer::new(&data, msg.author.id, msg.guild_id).await;
+    if reply_homebrew(ctx, msg, &data, &renderer, &to_search, library.get(&to_search).is_some()).await {
+        return;
+    }
+
     // let item: Option<&FullLibraryType> = library.get(&to_search);
 
     if let Some(val) = library.get(&to_search) {
-        reply!(ctx, msg, val);
+        reply!(ctx, msg, renderer => val);
         return;
     }
     // else nothing directly matching that name
 
     if !has_reaction_perm(ctx, msg.channel_id).await {
-        let to_say = match library.search_lib_obj(&to_search) {
-            Ok(val) => val.to_string(),
-            Err(val) => format!("Did you mean: {}", val.iter().map(|a| format!("{} ({})",a.get_name(), a.get_kind())).collect::<Vec<String>>().join(", ")),
+        match library.search_lib_obj(&to_search) {
+            Ok(val) => reply!(ctx, msg, renderer => val),
+            Err(val) => reply!(ctx, msg, format!("Did you mean: {}", val.iter().map(|a| format!("{} ({})",a.get_name(), a.get_kind())).collect::<Vec<String>>().join(", ")), false),
         };
-        reply!(ctx, msg, to_say, false);
         return;
     }
 
@@ -166,7 +170,7 @@ pub(crate) async fn search_full_library(ctx: &Context, msg: &Message, args: &[&s
 
     // only one item was returned, print it
     if res.len() == 1 {
-        reply!(ctx, msg, res[0]);
+        reply!(ctx, msg, renderer => res[0]);
         return;
     }
 
@@ -194,7 +198,7 @@ pub(crate) async fn search_full_library(ctx: &Context, msg: &Message, args: &[&s
     };
 
     if let Some(num) = reaction_did_you_mean(ctx, &msg_to_await, msg.author.id, res.len()).await {
-        if let Err(why) = edit_message_by_id(ctx, msg_to_await.channel_id.0, msg_to_await.id.0, res[num]).await {
+        if let Err(why) = renderer.edit(ctx, &msg_to_await, res[num]).await {
             println!("Could not edit message: {:?}", why);
         }
     }
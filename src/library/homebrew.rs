@@ -0,0 +1,529 @@
+use serde::{Deserialize, Serialize};
+use serenity::{
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::{channel::Message, id::GuildId},
+    prelude::*,
+};
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use simple_error::SimpleError;
+
+use crate::library::{
+    battlechip::BattleChip,
+    chip_library::{parse_chip_text, ChipLibrary},
+    render::Renderer,
+    Library,
+};
+
+const HOMEBREW_FILE: &str = "./homebrew.json";
+
+/// How long a homebrew link has to respond before it is given up on
+const HOMEBREW_TIMEOUT: Duration = Duration::from_secs(15);
+
+const HOMEBREW_NOTE: &str = "Homebrew chip for this server";
+
+/// The largest homebrew chip text which will be accepted, in bytes
+const MAX_HOMEBREW_SIZE: usize = 256 * 1024;
+
+/// What happens when a homebrew chip has the same name as an official chip
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictRule {
+    /// The official chip is kept and the homebrew one is skipped
+    #[default]
+    Official,
+    /// The homebrew chip replaces the official one in that guild
+    Homebrew,
+}
+
+impl std::str::FromStr for ConflictRule {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<ConflictRule, SimpleError> {
+        match to_parse.to_lowercase().as_str() {
+            "official" | "skip" => Ok(ConflictRule::Official),
+            "homebrew" | "override" => Ok(ConflictRule::Homebrew),
+            _ => Err(SimpleError::new(format!(
+                "{} is not a conflict rule, use official or homebrew",
+                to_parse
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for ConflictRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictRule::Official => write!(f, "official chips win"),
+            ConflictRule::Homebrew => write!(f, "homebrew chips win"),
+        }
+    }
+}
+
+/// The chip text a guild registered, kept so it can be parsed again on a restart or reload
+#[derive(Serialize, Deserialize)]
+struct HomebrewSource {
+    text: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    conflicts: ConflictRule,
+}
+
+/// A guild's parsed homebrew chips
+#[derive(Default)]
+struct GuildHomebrew {
+    chips: HashMap<String, Arc<BattleChip>>,
+    /// Homebrew chips which share a name with an official chip
+    shadowed: Vec<String>,
+    invalid: usize,
+}
+
+impl GuildHomebrew {
+    fn parse(source: &HomebrewSource, official: &ChipLibrary) -> GuildHomebrew {
+        let (parsed, diagnostics) = parse_chip_text(&source.text, "homebrew");
+        let mut homebrew = GuildHomebrew { invalid: diagnostics.len(), ..Default::default() };
+        for chip in parsed {
+            if official.get(&chip.name).is_some() {
+                homebrew.shadowed.push(chip.name.clone());
+                if source.conflicts == ConflictRule::Official {
+                    continue;
+                }
+            }
+            homebrew.chips.insert(chip.name.to_lowercase(), Arc::new(chip));
+        }
+        homebrew.shadowed.sort_unstable();
+        homebrew
+    }
+
+    fn summary(&self, conflicts: ConflictRule) -> String {
+        let mut to_ret = format!("{} homebrew chips loaded", self.chips.len());
+        if self.invalid > 0 {
+            to_ret.push_str(&format!(", {} entries could not be parsed and were skipped", self.invalid));
+        }
+        if !self.shadowed.is_empty() {
+            let outcome = match conflicts {
+                ConflictRule::Official => "skipped",
+                ConflictRule::Homebrew => "replace the official chips",
+            };
+            to_ret.push_str(&format!(
+                "\nThese share a name with an official chip and {}: {}",
+                outcome,
+                self.shadowed.join(", ")
+            ));
+        }
+        to_ret
+    }
+}
+
+/// Homebrew chips registered by each guild, layered over the official chips only in that guild
+#[derive(Default)]
+pub struct HomebrewChips {
+    sources: HashMap<u64, HomebrewSource>,
+    parsed: HashMap<u64, GuildHomebrew>,
+}
+
+impl HomebrewChips {
+    /// Loads the saved homebrew chip text and parses it against the official chips
+    pub async fn import(official: &ChipLibrary) -> RwLock<HomebrewChips> {
+        let mut homebrew = HomebrewChips::default();
+        if let Ok(text) = tokio::fs::read_to_string(HOMEBREW_FILE).await {
+            match serde_json::from_str(&text) {
+                Ok(sources) => homebrew.sources = sources,
+                Err(why) => {
+                    eprintln!("Could not parse {}, starting with no homebrew: {}", HOMEBREW_FILE, why)
+                }
+            }
+        }
+        homebrew.rebuild(official);
+        RwLock::new(homebrew)
+    }
+
+    async fn save(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string(&self.sources)?;
+        tokio::fs::write(HOMEBREW_FILE, json).await?;
+        Ok(())
+    }
+
+    /// Parses every guild's homebrew again, for when the official chips change
+    /// returns how many guilds have homebrew chips which share a name with an official chip
+    pub fn rebuild(&mut self, official: &ChipLibrary) -> usize {
+        self.parsed = self
+            .sources
+            .iter()
+            .map(|(guild, source)| (*guild, GuildHomebrew::parse(source, official)))
+            .collect();
+        self.parsed.values().filter(|homebrew| !homebrew.shadowed.is_empty()).count()
+    }
+
+    fn set(&mut self, guild: GuildId, text: String, url: Option<String>, official: &ChipLibrary) -> String {
+        let conflicts = self.sources.get(&guild.0).map(|source| source.conflicts).unwrap_or_default();
+        let source = HomebrewSource { text, url, conflicts };
+        let parsed = GuildHomebrew::parse(&source, official);
+        let summary = parsed.summary(conflicts);
+        self.sources.insert(guild.0, source);
+        self.parsed.insert(guild.0, parsed);
+        summary
+    }
+
+    /// Finds a guild's homebrew chip by its exact name, or by part of its name if the
+    /// official chips have nothing with exactly that name
+    pub fn find(&self, guild: Option<GuildId>, name: &str, official_exact: bool) -> Option<&Arc<BattleChip>> {
+        let chips = &self.parsed.get(&guild?.0)?.chips;
+        let name = name.trim().to_lowercase();
+        if let Some(chip) = chips.get(&name) {
+            return Some(chip);
+        }
+        if official_exact {
+            return None;
+        }
+
+        let mut found = chips.iter().filter(|(key, _)| key.contains(&name)).map(|(_, chip)| chip);
+        match (found.next(), found.next()) {
+            (Some(chip), None) => Some(chip),
+            _ => None,
+        }
+    }
+}
+
+impl HomebrewChips {
+    /// Searches for a chip the way a guild sees them, its homebrew chips layered over the official ones
+    pub fn search<'a>(
+        &'a self,
+        guild: Option<GuildId>,
+        official: &'a ChipLibrary,
+        name: &str,
+    ) -> Result<&'a Arc<BattleChip>, Vec<&'a Arc<BattleChip>>> {
+        match self.find(guild, name, official.get(name).is_some()) {
+            Some(chip) => Ok(chip),
+            None => official.search_lib_obj(name),
+        }
+    }
+}
+
+impl TypeMapKey for HomebrewChips {
+    type Value = RwLock<HomebrewChips>;
+}
+
+/// Whether an address is somewhere only the bot's own network could reach, such as loopback or a LAN
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // shared address space used by carrier-grade NAT
+                || (first == 100 && (64..128).contains(&second))
+                || first == 0
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local and link local
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(|ip| is_internal(IpAddr::V4(ip)))
+        }
+    }
+}
+
+/// Downloads homebrew chip text from a public https link, giving up once it passes `MAX_HOMEBREW_SIZE`
+async fn fetch_homebrew(url: &str) -> Result<String, SimpleError> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| SimpleError::new("that is not a valid link"))?;
+    if parsed.scheme() != "https" {
+        return Err(SimpleError::new("only https links can be used"));
+    }
+    let host = parsed.host_str().ok_or_else(|| SimpleError::new("that link has no host"))?.to_owned();
+    let port = parsed.port_or_known_default().unwrap_or(443);
+
+    let addrs = tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+        .await
+        .map_err(|_| SimpleError::new("could not find that host"))?
+        .collect::<Vec<SocketAddr>>();
+    let addr = match addrs.first() {
+        Some(addr) if addrs.iter().all(|addr| !is_internal(addr.ip())) => *addr,
+        Some(_) => return Err(SimpleError::new("that link points to a private address")),
+        None => return Err(SimpleError::new("could not find that host")),
+    };
+
+    // pin the checked address so the host cannot resolve somewhere else for the request itself,
+    // and don't follow redirects, which could lead anywhere
+    let client = reqwest::Client::builder()
+        .resolve(&host, addr)
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(HOMEBREW_TIMEOUT)
+        .build()
+        .map_err(|_| SimpleError::new("could not make a request"))?;
+
+    let mut response = client
+        .get(parsed)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|why| SimpleError::new(format!("the request failed: {}", why)))?;
+
+    let too_large = || SimpleError::new("that is too large to be a homebrew chip list");
+    if response.content_length().is_some_and(|len| len > MAX_HOMEBREW_SIZE as u64) {
+        return Err(too_large());
+    }
+
+    let mut body = vec![];
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|why| SimpleError::new(format!("the download failed: {}", why)))?
+    {
+        if body.len() + chunk.len() > MAX_HOMEBREW_SIZE {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let text = String::from_utf8(body).map_err(|_| SimpleError::new("that link is not text"))?;
+    Ok(ChipLibrary::clean_chip_text(&text))
+}
+
+/// Reply with the guild's homebrew chip with that name, marked as homebrew, returning whether there was one
+pub(crate) async fn reply_homebrew(
+    ctx: &Context,
+    msg: &Message,
+    data: &TypeMap,
+    renderer: &Renderer<'_>,
+    name: &str,
+    official_exact: bool,
+) -> bool {
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let homebrew = homebrew_lock.read().await;
+    let chip = match homebrew.find(msg.guild_id, name, official_exact) {
+        Some(chip) => chip,
+        None => return false,
+    };
+
+    if let Err(why) = renderer.reply_noted(ctx, msg, chip, Some(HOMEBREW_NOTE)).await {
+        println!("Could not send reply: {:?}", why);
+    }
+    true
+}
+
+#[group]
+#[prefixes("homebrew", "hb")]
+#[only_in(guilds)]
+#[default_command(show_homebrew)]
+#[commands(show_homebrew, set_homebrew, refresh_homebrew, homebrew_conflicts, clear_homebrew)]
+/// A group of commands for adding homebrew chips which are only used in this server
+/// Homebrew chips are found when looking up, rolling damage for, getting the DCs of, or comparing chips by name,
+/// searches, random chips, and folders only use the official chips
+struct Homebrew;
+
+#[command("show")]
+/// Show this server's homebrew chips
+async fn show_homebrew(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let data = ctx.data.read().await;
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let homebrew = homebrew_lock.read().await;
+
+    let (source, parsed) = match (homebrew.sources.get(&guild.0), homebrew.parsed.get(&guild.0)) {
+        (Some(source), Some(parsed)) => (source, parsed),
+        _ => {
+            reply!(ctx, msg, "This server has no homebrew chips");
+            return Ok(());
+        }
+    };
+
+    let mut names = parsed.chips.values().map(|chip| chip.name.as_str()).collect::<Vec<&str>>();
+    names.sort_unstable();
+    let mut to_send = vec![
+        parsed.summary(source.conflicts),
+        format!("When names conflict {}", source.conflicts),
+    ];
+    if let Some(url) = &source.url {
+        to_send.push(format!("Loaded from <{}>", url));
+    }
+    to_send.push(names.join(", "));
+    long_say!(ctx, msg, to_send, "\n");
+    Ok(())
+}
+
+#[command("set")]
+#[required_permissions("MANAGE_GUILD")]
+/// Set this server's homebrew chips, replacing any it had before
+/// Attach a text file of chips written like the official chip list, or give an https link to one
+#[example = "https://example.com/homebrew.txt"]
+async fn set_homebrew(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let url = args.rest().trim().trim_start_matches('<').trim_end_matches('>');
+    let (text, url) = match msg.attachments.first() {
+        Some(attachment) if attachment.size as usize > MAX_HOMEBREW_SIZE => {
+            reply!(ctx, msg, "That file is too large to be a homebrew chip list");
+            return Ok(());
+        }
+        Some(attachment) => match attachment.download().await.map(String::from_utf8) {
+            Ok(Ok(text)) => (ChipLibrary::clean_chip_text(&text), None),
+            Ok(Err(_)) => {
+                reply!(ctx, msg, "That file is not text");
+                return Ok(());
+            }
+            Err(why) => {
+                eprintln!("Could not download homebrew chips: {:?}", why);
+                reply!(ctx, msg, "Could not download that file, try a link instead");
+                return Ok(());
+            }
+        },
+        None if url.is_empty() => {
+            reply!(ctx, msg, "You must attach a file of homebrew chips or give a link to one");
+            return Ok(());
+        }
+        None => match fetch_homebrew(url).await {
+            Ok(text) => (text, Some(url.to_owned())),
+            Err(why) => {
+                reply!(ctx, msg, format!("Could not get the homebrew chips from that link: {}", why));
+                return Ok(());
+            }
+        },
+    };
+
+    if text.len() > MAX_HOMEBREW_SIZE {
+        reply!(ctx, msg, "That is too large to be a homebrew chip list");
+        return Ok(());
+    }
+
+    save_homebrew(ctx, msg, guild, text, url).await;
+    Ok(())
+}
+
+#[command("refresh")]
+#[required_permissions("MANAGE_GUILD")]
+/// Get this server's homebrew chips again from the link they were set from
+async fn refresh_homebrew(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let url = {
+        let data = ctx.data.read().await;
+        let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+        let homebrew = homebrew_lock.read().await;
+        homebrew.sources.get(&guild.0).and_then(|source| source.url.clone())
+    };
+
+    let url = match url {
+        Some(url) => url,
+        None => {
+            reply!(ctx, msg, "This server's homebrew chips were not set from a link");
+            return Ok(());
+        }
+    };
+
+    match fetch_homebrew(&url).await {
+        Ok(text) => save_homebrew(ctx, msg, guild, text, Some(url)).await,
+        Err(why) => reply!(ctx, msg, format!("Could not get the homebrew chips: {}", why)),
+    }
+    Ok(())
+}
+
+async fn save_homebrew(ctx: &Context, msg: &Message, guild: GuildId, text: String, url: Option<String>) {
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let mut homebrew = homebrew_lock.write().await;
+
+    let summary = homebrew.set(guild, text, url, &library);
+    if let Err(why) = homebrew.save().await {
+        eprintln!("Could not save homebrew: {:?}", why);
+        reply!(ctx, msg, format!("{}\nThese could not be saved and will be lost on a restart", summary));
+        return;
+    }
+    reply!(ctx, msg, summary);
+}
+
+#[command("conflicts")]
+#[required_permissions("MANAGE_GUILD")]
+/// Choose whether official or homebrew chips are used when they have the same name
+#[example = "official"]
+#[example = "homebrew"]
+async fn homebrew_conflicts(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let rule = match args.rest().trim().parse::<ConflictRule>() {
+        Ok(rule) => rule,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let mut homebrew = homebrew_lock.write().await;
+
+    let source = match homebrew.sources.get_mut(&guild.0) {
+        Some(source) => source,
+        None => {
+            reply!(ctx, msg, "This server has no homebrew chips");
+            return Ok(());
+        }
+    };
+    source.conflicts = rule;
+    let parsed = GuildHomebrew::parse(source, &library);
+    homebrew.parsed.insert(guild.0, parsed);
+
+    if let Err(why) = homebrew.save().await {
+        eprintln!("Could not save homebrew: {:?}", why);
+    }
+    reply!(ctx, msg, format!("When names conflict {} in this server", rule));
+    Ok(())
+}
+
+#[command("clear")]
+#[required_permissions("MANAGE_GUILD")]
+/// Remove all of this server's homebrew chips
+async fn clear_homebrew(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let data = ctx.data.read().await;
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let mut homebrew = homebrew_lock.write().await;
+
+    homebrew.parsed.remove(&guild.0);
+    if homebrew.sources.remove(&guild.0).is_none() {
+        reply!(ctx, msg, "This server has no homebrew chips");
+        return Ok(());
+    }
+
+    if let Err(why) = homebrew.save().await {
+        eprintln!("Could not save homebrew: {:?}", why);
+    }
+    reply!(ctx, msg, "Removed this server's homebrew chips");
+    Ok(())
+}
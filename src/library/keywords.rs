@@ -0,0 +1,223 @@
+use regex::Regex;
+use serenity::prelude::*;
+
+use std::collections::HashMap;
+
+use crate::library::{
+    blights::{Blights, Panels, StatusLike, Statuses},
+    chip_library::ChipLibrary,
+    ncp_library::NCPLibrary,
+    virus_library::VirusLibrary,
+    Library, LibraryObject,
+};
+
+/// The kinds of definitions a description can mention
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum KeywordKind {
+    Status,
+    Panel,
+    Blight,
+}
+
+impl std::fmt::Display for KeywordKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeywordKind::Status => write!(f, "status"),
+            KeywordKind::Panel => write!(f, "panel"),
+            KeywordKind::Blight => write!(f, "blight"),
+        }
+    }
+}
+
+/// A status, panel, or blight mentioned in a description
+#[derive(Clone)]
+pub struct Keyword {
+    pub kind: KeywordKind,
+    pub key: String,
+}
+
+impl std::fmt::Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut chars = self.key.chars();
+        match chars.next() {
+            Some(first) => write!(f, "{}{} ({})", first.to_uppercase(), chars.as_str(), self.kind),
+            None => Ok(()),
+        }
+    }
+}
+
+struct KeywordMatcher {
+    keyword: Keyword,
+    pattern: Regex,
+}
+
+/// The ways each status is written in descriptions, a status not listed here only matches its name
+const STATUS_FORMS: &[(&str, &[&str])] = &[
+    ("angry", &["angry", "angered", "anger", "angers"]),
+    ("aura", &["aura", "auras"]),
+    ("barrier", &["barrier", "barriers"]),
+    ("blight", &["blight", "blighted", "blights"]),
+    ("blind", &["blind", "blinded", "blinds", "blindness"]),
+    ("confusion", &["confusion", "confused", "confuse", "confuses"]),
+    ("fatigue", &["fatigue", "fatigued", "fatigues"]),
+    ("invisible", &["invisible", "invisibility"]),
+    ("lock", &["lock", "locked", "locks"]),
+    ("paralysis", &["paralysis", "paralyzed", "paralyze", "paralyzes", "paralysed", "paralyse", "paralyses"]),
+    ("shield", &["shield", "shields", "shielded"]),
+    ("stagger", &["stagger", "staggered", "staggers"]),
+];
+
+impl KeywordMatcher {
+    fn new(kind: KeywordKind, key: &str) -> Option<KeywordMatcher> {
+        let pattern = match kind {
+            // blights are written as Blight (Fire), the element alone is far too common
+            KeywordKind::Blight => format!(r"(?i)\bblight\s\({}\)", regex::escape(key)),
+            KeywordKind::Status => {
+                let forms = STATUS_FORMS
+                    .iter()
+                    .find(|(status, _)| status.eq_ignore_ascii_case(key))
+                    .map_or_else(|| regex::escape(key), |(_, forms)| forms.join("|"));
+                format!(r"(?i)\b(?:{})\b", forms)
+            }
+            // panel names like poison or sand are common words, so only match them as a panel
+            KeywordKind::Panel => format!(r"(?i)\b{}\s+panels?\b", regex::escape(key)),
+        };
+        Some(KeywordMatcher {
+            keyword: Keyword { kind, key: key.to_owned() },
+            pattern: Regex::new(&pattern).ok()?,
+        })
+    }
+}
+
+/// Which statuses, panels, and blights each chip, NCP, and virus mentions in its description
+#[derive(Default)]
+pub struct KeywordIndex {
+    /// Keyed by formatted name, so a chip and a virus which share a name are kept apart
+    related: HashMap<String, Vec<Keyword>>,
+    /// The chips and viruses which mention each status, formatted with their kind
+    status_users: HashMap<String, Vec<String>>,
+}
+
+impl KeywordIndex {
+    pub fn build(
+        statuses: &Statuses,
+        panels: &Panels,
+        blights: &Blights,
+        chips: &ChipLibrary,
+        ncps: &NCPLibrary,
+        viruses: &VirusLibrary,
+    ) -> KeywordIndex {
+        let matchers = statuses
+            .keys()
+            .into_iter()
+            .filter_map(|key| KeywordMatcher::new(KeywordKind::Status, key))
+            .chain(panels.keys().into_iter().filter_map(|key| KeywordMatcher::new(KeywordKind::Panel, key)))
+            .chain(blights.keys().into_iter().filter_map(|key| KeywordMatcher::new(KeywordKind::Blight, key)))
+            .collect::<Vec<KeywordMatcher>>();
+
+        let mut index = KeywordIndex::default();
+        let descriptions = chips
+            .get_collection()
+            .values()
+            .map(|chip| (chip.get_formatted_name(), chip.description.as_str(), true))
+            .chain(
+                ncps.get_collection()
+                    .values()
+                    .map(|ncp| (ncp.get_formatted_name(), ncp.description.as_str(), false)),
+            )
+            .chain(
+                viruses
+                    .get_collection()
+                    .values()
+                    .map(|virus| (virus.get_formatted_name(), virus.description.as_str(), true)),
+            );
+
+        for (formatted, description, inflicts) in descriptions {
+            let found = matchers
+                .iter()
+                .filter(|matcher| matcher.pattern.is_match(description))
+                .map(|matcher| matcher.keyword.clone())
+                .collect::<Vec<Keyword>>();
+            if found.is_empty() {
+                continue;
+            }
+
+            if inflicts {
+                for keyword in found.iter().filter(|keyword| keyword.kind == KeywordKind::Status) {
+                    index.status_users.entry(keyword.key.clone()).or_default().push(formatted.clone());
+                }
+            }
+            index.related.insert(formatted.to_lowercase(), found);
+        }
+
+        for users in index.status_users.values_mut() {
+            users.sort_unstable();
+        }
+        index
+    }
+
+    /// The statuses, panels, and blights a chip, NCP, or virus mentions, joined by commas
+    pub fn related<T: LibraryObject + ?Sized>(&self, obj: &T) -> Option<String> {
+        self.related
+            .get(&obj.get_formatted_name().to_lowercase())
+            .map(|related| related.iter().map(Keyword::to_string).collect::<Vec<String>>().join(", "))
+    }
+
+    /// Every chip and virus which mentions a status
+    pub fn status_users(&self, status: &str) -> Option<&[String]> {
+        self.status_users.get(&status.to_lowercase()).map(Vec::as_slice)
+    }
+}
+
+impl TypeMapKey for KeywordIndex {
+    type Value = RwLock<KeywordIndex>;
+}
+
+/// Rebuilds the keyword index from the current libraries, for after a reload
+pub(crate) async fn rebuild_keywords(data: &TypeMap, chips: &ChipLibrary, ncps: &NCPLibrary, viruses: &VirusLibrary) {
+    let statuses = data.get::<Statuses>().expect("statuses not found").read().await;
+    let panels = data.get::<Panels>().expect("panels not found").read().await;
+    let blights = data.get::<Blights>().expect("blights not found").read().await;
+    let index = KeywordIndex::build(&statuses, &panels, &blights, chips, ncps, viruses);
+    *data.get::<KeywordIndex>().expect("keyword index not found").write().await = index;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(kind: KeywordKind, key: &str, text: &str) -> bool {
+        KeywordMatcher::new(kind, key).expect("keyword did not compile").pattern.is_match(text)
+    }
+
+    #[test]
+    fn matches_other_forms_of_a_status() {
+        assert!(matches(KeywordKind::Status, "paralysis", "The target is Paralyzed for 1 round."));
+        assert!(matches(KeywordKind::Status, "confusion", "Confused targets attack their allies."));
+        assert!(matches(KeywordKind::Status, "stagger", "Staggers the target."));
+        assert!(matches(KeywordKind::Status, "blind", "Inflicts blindness."));
+        assert!(matches(KeywordKind::Status, "Aura", "Grants an aura."));
+    }
+
+    #[test]
+    fn does_not_match_inside_other_words() {
+        assert!(!matches(KeywordKind::Status, "lock", "Blocks one attack."));
+        assert!(!matches(KeywordKind::Status, "shield", "Shieldbreaker."));
+    }
+
+    #[test]
+    fn matches_panels_only_as_panels() {
+        assert!(matches(KeywordKind::Panel, "poison", "Turns the target's panel into a Poison panel."));
+        assert!(matches(KeywordKind::Panel, "metal", "Creates three Metal Panels."));
+        assert!(!matches(KeywordKind::Panel, "poison", "Deals poison damage over time."));
+        assert!(!matches(KeywordKind::Panel, "sea", "A wave rises from the sea."));
+        assert!(!matches(KeywordKind::Panel, "holy", "A holy light heals the user."));
+        assert!(!matches(KeywordKind::Panel, "ice", "Freezes the target in ice."));
+    }
+
+    #[test]
+    fn matches_blights_only_with_their_element() {
+        assert!(matches(KeywordKind::Blight, "fire", "Inflicts Blight (Fire)."));
+        assert!(!matches(KeywordKind::Blight, "fire", "A blast of fire."));
+    }
+}
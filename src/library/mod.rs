@@ -1,21 +1,32 @@
 pub(crate) mod battlechip;
 pub(crate) mod blights;
+pub(crate) mod changelog;
 pub(crate) mod chip_library;
 pub(crate) mod elements;
+pub(crate) mod folder;
 pub(crate) mod full_library;
+pub(crate) mod homebrew;
+pub(crate) mod keywords;
+pub(crate) mod navicust;
 pub(crate) mod ncp_library;
+pub(crate) mod render;
+#[cfg(test)]
+pub(crate) mod test_chips;
 pub(crate) mod virus_library;
 use std::{collections::HashMap, sync::Arc};
 
 use serenity::{
     prelude::*, 
+    builder::CreateEmbed,
     model::channel::Message,
     async_trait
 };
 
 use strsim::jaro_winkler;
 
-use crate::util::{reaction_did_you_mean, has_reaction_perm, edit_message_by_id};
+use crate::util::{reaction_did_you_mean, has_reaction_perm};
+
+use render::Renderer;
 
 use std::ops::Deref;
 
@@ -29,6 +40,13 @@ pub trait LibraryObject: std::fmt::Display + Send + Sync {
     fn get_formatted_name(&self) -> String {
         format!("{} ({})", self.get_name(), self.get_kind())
     }
+
+    /// An embed of this object, for users and servers which prefer embeds to plain text
+    fn embed(&self) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+        embed.title(self.get_formatted_name()).description(self.to_string());
+        embed
+    }
 }
 
 impl<T: LibraryObject + ?Sized> LibraryObject for Arc<T> {
@@ -44,6 +62,9 @@ impl<T: LibraryObject + ?Sized> LibraryObject for Arc<T> {
         self.deref().get_formatted_name()
     }
 
+    fn embed(&self) -> CreateEmbed {
+        self.deref().embed()
+    }
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -132,10 +153,10 @@ pub trait Library: TypeMapKey {
         Err(item_search)
     }
 
-    async fn reaction_name_search(&self, ctx: &Context, msg: &Message, to_get: &str) {
+    async fn reaction_name_search(&self, ctx: &Context, msg: &Message, to_get: &str, renderer: &Renderer<'_>) {
         let list = match self.search_lib_obj(to_get) {
             Ok(val) => {
-                reply!(ctx, msg, val);
+                reply!(ctx, msg, renderer => val);
                 return;
             },
             Err(val) => val,
@@ -170,7 +191,7 @@ pub trait Library: TypeMapKey {
         };
     
         if let Some(num) = reaction_did_you_mean(ctx, &msg_to_await, msg.author.id, list.len()).await {
-            if let Err(why) = edit_message_by_id(ctx, msg_to_await.channel_id.0, msg_to_await.id.0, list[num]).await {
+            if let Err(why) = renderer.edit(ctx, &msg_to_await, list[num]).await {
                 println!("Could not edit message: {:?}", why);
             }
         }
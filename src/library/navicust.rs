@@ -0,0 +1,431 @@
+use serde::{Deserialize, Serialize};
+use serenity::{
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::{channel::Message, id::UserId},
+    prelude::*,
+};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use simple_error::SimpleError;
+
+use crate::{
+    bot_data::{BotData, NaviCustRules},
+    library::{
+        ncp_library::{NCPLibrary, NCP},
+        Library,
+    },
+    LibraryObject,
+};
+
+const NAVICUST_FILE: &str = "./navicust.json";
+
+/// Gray parts are bugs, they are limited on their own and do not count as a colour
+const BUG_COLOUR: &str = "gray";
+
+/// A user's installed Navi-Customizer parts
+#[derive(Serialize, Deserialize, Default)]
+pub struct NaviCust {
+    parts: BTreeSet<String>,
+    /// The EB this user's navi can spend, the configured default is used when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    budget: Option<u16>,
+    /// Parts which were no longer in the library at the last reload
+    #[serde(default)]
+    flagged: BTreeSet<String>,
+}
+
+impl NaviCust {
+    fn budget(&self, rules: &NaviCustRules) -> u16 {
+        self.budget.unwrap_or(rules.default_budget)
+    }
+
+    /// The installed parts which are still in the library
+    fn installed<'a>(&'a self, library: &'a NCPLibrary) -> impl Iterator<Item = &'a NCP> + 'a {
+        self.parts.iter().filter_map(move |name| library.get(name)).map(|ncp| ncp.as_ref())
+    }
+
+    fn eb_used(&self, library: &NCPLibrary) -> u16 {
+        self.installed(library).map(|ncp| u16::from(ncp.e_b_cost)).sum()
+    }
+
+    /// Every colour used, other than Gray
+    fn colours(&self, library: &NCPLibrary) -> BTreeSet<String> {
+        self.installed(library)
+            .map(|ncp| ncp.color.to_lowercase())
+            .filter(|colour| colour != BUG_COLOUR)
+            .collect()
+    }
+
+    fn bug_parts(&self, library: &NCPLibrary) -> usize {
+        self.installed(library)
+            .filter(|ncp| ncp.color.eq_ignore_ascii_case(BUG_COLOUR))
+            .count()
+    }
+
+    /// Checks that installing a part keeps this loadout within its budget and the colour rules
+    fn check_add(&self, ncp: &NCP, rules: &NaviCustRules, library: &NCPLibrary) -> Result<(), SimpleError> {
+        if self.parts.contains(&ncp.name) {
+            return Err(SimpleError::new(format!("{} is already installed", ncp.name)));
+        }
+
+        // a missing part's cost is unknown, so the budget can't be checked until it is dealt with
+        let missing = self.parts.iter().filter(|name| library.get(name).is_none()).cloned().collect::<Vec<String>>();
+        if !missing.is_empty() {
+            return Err(SimpleError::new(format!(
+                "These parts are no longer in the library, remove them before installing anything else: {}",
+                missing.join(", ")
+            )));
+        }
+
+        let budget = self.budget(rules);
+        let used = self.eb_used(library) + u16::from(ncp.e_b_cost);
+        if used > budget {
+            return Err(SimpleError::new(format!(
+                "That would use {} EB, but your budget is {} EB",
+                used, budget
+            )));
+        }
+
+        if ncp.color.eq_ignore_ascii_case(BUG_COLOUR) {
+            let bugs = self.bug_parts(library) + 1;
+            if bugs > rules.max_bug_parts {
+                return Err(SimpleError::new(format!(
+                    "That would make {} Gray bug parts, but loadouts can only hold {}",
+                    bugs, rules.max_bug_parts
+                )));
+            }
+            return Ok(());
+        }
+
+        let mut colours = self.colours(library);
+        colours.insert(ncp.color.to_lowercase());
+        if colours.len() > rules.max_colours {
+            return Err(SimpleError::new(format!(
+                "That would make {} colours, but loadouts can only use {} other than Gray",
+                colours.len(),
+                rules.max_colours
+            )));
+        }
+        Ok(())
+    }
+
+    /// Flags every part which is not in the library, returns true if any are flagged
+    fn flag_missing(&mut self, library: &NCPLibrary) -> bool {
+        self.flagged = self
+            .parts
+            .iter()
+            .filter(|name| library.get(name).is_none())
+            .cloned()
+            .collect();
+        !self.flagged.is_empty()
+    }
+
+    /// A short list of the installed parts grouped by colour
+    fn summary(&self, rules: &NaviCustRules, library: &NCPLibrary) -> String {
+        let mut by_colour: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for ncp in self.installed(library) {
+            by_colour
+                .entry(ncp.color.as_str())
+                .or_default()
+                .push(format!("{} ({} EB)", ncp.name, ncp.e_b_cost));
+        }
+
+        // the counts only cover parts still in the library, like the EB used, the rest are listed below
+        let mut summary = format!(
+            "{}/{} EB used, {} parts, {} colours",
+            self.eb_used(library),
+            self.budget(rules),
+            self.installed(library).count(),
+            self.colours(library).len()
+        );
+        for (colour, parts) in by_colour {
+            summary.push_str(&format!("\n{}: {}", colour, parts.join(", ")));
+        }
+        if !self.flagged.is_empty() {
+            summary.push_str(&format!(
+                "\nNo longer in the library: {}",
+                self.flagged.iter().cloned().collect::<Vec<String>>().join(", ")
+            ));
+        }
+        summary
+    }
+
+    /// Every installed part with its description, for copying onto a character sheet
+    fn sheet(&self, rules: &NaviCustRules, library: &NCPLibrary) -> String {
+        let mut sheet = format!("Navi-Cust ({}/{} EB)", self.eb_used(library), self.budget(rules));
+        for ncp in self.installed(library) {
+            sheet.push_str(&format!(
+                "\n{} ({} EB, {}) - {}",
+                ncp.name, ncp.e_b_cost, ncp.color, ncp.description
+            ));
+        }
+        for name in &self.flagged {
+            sheet.push_str(&format!("\n{} (no longer in the library)", name));
+        }
+        sheet
+    }
+}
+
+/// The Navi-Cust loadout saved by each user
+#[derive(Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct NaviCusts(HashMap<u64, NaviCust>);
+
+impl NaviCusts {
+    /// Loads the saved loadouts, starting fresh if there are none yet
+    pub async fn import() -> RwLock<NaviCusts> {
+        let navicusts = match tokio::fs::read_to_string(NAVICUST_FILE).await {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|why| {
+                eprintln!("Could not parse {}, starting with no Navi-Cust loadouts: {}", NAVICUST_FILE, why);
+                NaviCusts::default()
+            }),
+            Err(_) => NaviCusts::default(),
+        };
+        RwLock::new(navicusts)
+    }
+
+    pub async fn save(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string(&self.0)?;
+        tokio::fs::write(NAVICUST_FILE, json).await?;
+        Ok(())
+    }
+
+    fn get(&self, user: UserId) -> Option<&NaviCust> {
+        self.0.get(&user.0)
+    }
+
+    fn get_mut(&mut self, user: UserId) -> Option<&mut NaviCust> {
+        self.0.get_mut(&user.0)
+    }
+
+    fn get_or_create(&mut self, user: UserId) -> &mut NaviCust {
+        self.0.entry(user.0).or_default()
+    }
+
+    /// Flags parts missing from the library in every loadout, returns how many loadouts have any
+    pub fn flag_missing(&mut self, library: &NCPLibrary) -> usize {
+        self.0
+            .values_mut()
+            .map(|navicust| navicust.flag_missing(library))
+            .filter(|flagged| *flagged)
+            .count()
+    }
+}
+
+impl TypeMapKey for NaviCusts {
+    type Value = RwLock<NaviCusts>;
+}
+
+#[group]
+#[prefixes("navicust", "cust")]
+#[default_command(show_navicust)]
+#[commands(add_part, remove_part, show_navicust, set_budget, export_navicust, clear_navicust)]
+/// A group of commands for planning your Navi-Customizer loadout
+struct NaviCustomizer;
+
+#[command("add")]
+#[aliases("install")]
+/// Install a part in your Navi-Cust, checking it against your EB budget and the colour rules
+/// Parts which are no longer in the library must be removed before anything else is installed
+#[example = "Undershirt"]
+async fn add_part(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must supply the part to install");
+        return Ok(());
+    }
+
+    let to_get = args.rest().trim();
+    let data = ctx.data.read().await;
+    let rules = &data.get::<BotData>().expect("no bot data available").navicust_rules;
+    let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
+    let library = library_lock.read().await;
+    let navicusts_lock = data.get::<NaviCusts>().expect("navicusts not found");
+    let mut navicusts = navicusts_lock.write().await;
+
+    let ncp = match library.search_lib_obj(to_get) {
+        Ok(ncp) => ncp,
+        Err(ncps) => {
+            let to_say = ncps.iter().map(|a| a.get_name()).collect::<Vec<&str>>().join(", ");
+            reply!(ctx, msg, format!("Could not find {}, did you mean: {}", to_get, to_say));
+            return Ok(());
+        }
+    };
+
+    let navicust = navicusts.get_or_create(msg.author.id);
+    if let Err(why) = navicust.check_add(ncp, rules, &library) {
+        reply!(ctx, msg, why);
+        return Ok(());
+    }
+
+    navicust.parts.insert(ncp.name.clone());
+    let used = navicust.eb_used(&library);
+    let budget = navicust.budget(rules);
+
+    if let Err(why) = navicusts.save().await {
+        eprintln!("Could not save navicusts: {:?}", why);
+        reply!(ctx, msg, "Part installed, but your Navi-Cust could not be saved and will be lost on a restart");
+        return Ok(());
+    }
+
+    reply!(ctx, msg, format!("Installed {}, {}/{} EB used", ncp.name, used, budget));
+    Ok(())
+}
+
+#[command("remove")]
+#[aliases("rem", "uninstall")]
+/// Remove a part from your Navi-Cust
+#[example = "Undershirt"]
+async fn remove_part(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "You must supply the part to remove");
+        return Ok(());
+    }
+
+    let to_get = args.rest().trim();
+    let data = ctx.data.read().await;
+    let navicusts_lock = data.get::<NaviCusts>().expect("navicusts not found");
+    let mut navicusts = navicusts_lock.write().await;
+
+    // parts no longer in the library can still be removed, so match on the saved name
+    let found = navicusts.get_mut(msg.author.id).and_then(|navicust| {
+        let part = navicust.parts.iter().find(|part| part.eq_ignore_ascii_case(to_get))?.clone();
+        Some((navicust, part))
+    });
+    let (navicust, part) = match found {
+        Some(found) => found,
+        None => {
+            reply!(ctx, msg, format!("{} is not installed", to_get));
+            return Ok(());
+        }
+    };
+
+    navicust.parts.remove(&part);
+    navicust.flagged.remove(&part);
+
+    if let Err(why) = navicusts.save().await {
+        eprintln!("Could not save navicusts: {:?}", why);
+    }
+
+    reply!(ctx, msg, format!("Removed {}", part));
+    Ok(())
+}
+
+#[command("show")]
+#[aliases("list")]
+/// Show the parts installed in your Navi-Cust and how much EB they use
+async fn show_navicust(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let rules = &data.get::<BotData>().expect("no bot data available").navicust_rules;
+    let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
+    let library = library_lock.read().await;
+    let navicusts_lock = data.get::<NaviCusts>().expect("navicusts not found");
+    let navicusts = navicusts_lock.read().await;
+
+    match navicusts.get(msg.author.id).filter(|navicust| !navicust.parts.is_empty()) {
+        Some(navicust) => reply!(ctx, msg, format!("```\n{}\n```", navicust.summary(rules, &library))),
+        None => reply!(ctx, msg, "You have no parts installed, add one with navicust add"),
+    }
+    Ok(())
+}
+
+#[command("budget")]
+#[aliases("eb")]
+/// Show your EB budget, or set it to match your character, use "default" to go back to the server's
+#[example = "12"]
+#[example = "default"]
+async fn set_budget(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let rules = &data.get::<BotData>().expect("no bot data available").navicust_rules;
+    let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
+    let library = library_lock.read().await;
+    let navicusts_lock = data.get::<NaviCusts>().expect("navicusts not found");
+    let mut navicusts = navicusts_lock.write().await;
+
+    let budget = match args.rest().trim().to_lowercase().as_str() {
+        "" => {
+            let budget = navicusts.get(msg.author.id).map_or(rules.default_budget, |navicust| navicust.budget(rules));
+            reply!(ctx, msg, format!("Your EB budget is {}", budget));
+            return Ok(());
+        }
+        "default" | "clear" => None,
+        other => match other.parse::<u16>() {
+            Ok(budget) => Some(budget),
+            Err(_) => {
+                reply!(ctx, msg, format!("{} is not a valid EB budget", other));
+                return Ok(());
+            }
+        },
+    };
+
+    let navicust = navicusts.get_or_create(msg.author.id);
+    navicust.budget = budget;
+    let (used, budget) = (navicust.eb_used(&library), navicust.budget(rules));
+
+    if let Err(why) = navicusts.save().await {
+        eprintln!("Could not save navicusts: {:?}", why);
+    }
+
+    if used > budget {
+        reply!(
+            ctx,
+            msg,
+            format!("Your EB budget is now {}, but your parts use {} EB, remove some to fit", budget, used)
+        );
+    } else {
+        reply!(ctx, msg, format!("Your EB budget is now {}", budget));
+    }
+    Ok(())
+}
+
+#[command("export")]
+#[aliases("sheet")]
+/// Get your Navi-Cust with each part's description, for copying onto a character sheet
+async fn export_navicust(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let rules = &data.get::<BotData>().expect("no bot data available").navicust_rules;
+    let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
+    let library = library_lock.read().await;
+    let navicusts_lock = data.get::<NaviCusts>().expect("navicusts not found");
+    let navicusts = navicusts_lock.read().await;
+
+    let navicust = match navicusts.get(msg.author.id).filter(|navicust| !navicust.parts.is_empty()) {
+        Some(navicust) => navicust,
+        None => {
+            reply!(ctx, msg, "You have no parts installed, add one with navicust add");
+            return Ok(());
+        }
+    };
+
+    let sheet = navicust.sheet(rules, &library);
+    if let Err(why) = msg
+        .channel_id
+        .send_files(&ctx.http, vec![(sheet.as_bytes(), "navicust.txt")], |m| {
+            m.content("Your Navi-Cust")
+        })
+        .await
+    {
+        println!("Could not send navicust: {:?}", why);
+    }
+    Ok(())
+}
+
+#[command("clear")]
+/// Remove every part from your Navi-Cust, your budget is kept
+async fn clear_navicust(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let navicusts_lock = data.get::<NaviCusts>().expect("navicusts not found");
+    let mut navicusts = navicusts_lock.write().await;
+
+    if let Some(navicust) = navicusts.get_mut(msg.author.id) {
+        navicust.parts.clear();
+        navicust.flagged.clear();
+    }
+
+    if let Err(why) = navicusts.save().await {
+        eprintln!("Could not save navicusts: {:?}", why);
+    }
+
+    reply!(ctx, msg, "Your Navi-Cust is now empty");
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::{collections::HashMap, sync::Arc};
 
 use serde::Serialize;
 use serenity::{
+    builder::CreateEmbed,
     framework::standard::{macros::{command, group}, Args, CommandResult},
     model::channel::Message,
     prelude::*,
@@ -12,10 +13,11 @@ use serenity::{
 use serde_json;
 
 use crate::{
-    library::{Library, LibraryObject},
+    library::{render::{embed_value, Renderer}, Library, LibraryObject},
     ReloadReturnType,
 };
 use regex::Regex;
+use simple_error::SimpleError;
 use std::fmt::Formatter;
 
 use unicode_normalization::UnicodeNormalization;
@@ -42,6 +44,32 @@ impl LibraryObject for NCP {
         "NCP"
     }
 
+    fn embed(&self) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(&self.name)
+            .description(embed_value(&self.description))
+            .field("EB", self.e_b_cost, true)
+            .field("Colour", embed_value(&self.color), true);
+        if let Some(colour) = ncp_colour(&self.color) {
+            embed.colour(colour);
+        }
+        embed
+    }
+}
+
+/// The embed colour for a Navi-Customizer part colour
+fn ncp_colour(color: &str) -> Option<u32> {
+    match color.to_lowercase().as_str() {
+        "white" => Some(0xECF0F1),
+        "pink" => Some(0xF78FB3),
+        "yellow" => Some(0xF1C40F),
+        "green" => Some(0x2ECC71),
+        "blue" => Some(0x3498DB),
+        "red" => Some(0xE74C3C),
+        "gray" | "grey" => Some(0x7F8C8D),
+        _ => None,
+    }
 }
 
 impl NCP {
@@ -54,6 +82,11 @@ impl NCP {
             description: desc.into().nfc().collect::<String>(),
         }
     }
+
+    /// The name with its cost and color, for lists of parts
+    pub fn short_name(&self) -> String {
+        format!("{} ({} EB, {})", self.name, self.e_b_cost, self.color)
+    }
 }
 
 impl std::fmt::Display for NCP {
@@ -71,7 +104,48 @@ pub struct NCPLibrary {
     ncp_url: Arc<String>,
 }
 
-const COLORS: &[&str] = &["white", "pink", "yellow", "green", "blue", "red", "gray"];
+pub(crate) const COLORS: &[&str] = &["white", "pink", "yellow", "green", "blue", "red", "gray"];
+
+/// An inclusive range of EB costs, written as a single cost like 2 or a range like 1-3
+#[derive(Clone, Copy)]
+pub struct CostRange {
+    min: u8,
+    max: u8,
+}
+
+impl CostRange {
+    fn contains(self, cost: u8) -> bool {
+        self.min <= cost && cost <= self.max
+    }
+}
+
+impl std::str::FromStr for CostRange {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<CostRange, SimpleError> {
+        let bad_cost = || SimpleError::new(format!("{} is not an EB cost, try 2 or 1-3", to_parse));
+        let (min, max) = match to_parse.trim().split_once('-') {
+            Some((min, max)) => (min.trim(), max.trim()),
+            None => (to_parse.trim(), to_parse.trim()),
+        };
+        let min = min.parse::<u8>().map_err(|_| bad_cost())?;
+        let max = max.parse::<u8>().map_err(|_| bad_cost())?;
+        if min > max {
+            return Err(bad_cost());
+        }
+        Ok(CostRange { min, max })
+    }
+}
+
+impl std::fmt::Display for CostRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "{} EB", self.min)
+        } else {
+            write!(f, "{}-{} EB", self.min, self.max)
+        }
+    }
+}
 
 impl Library for NCPLibrary {
     type LibObj = Arc<NCP>;
@@ -190,6 +264,40 @@ impl NCPLibrary {
         self.search_any(color, |a, b| a.color.to_lowercase() == b.to_lowercase())
     }
 
+    /// Every NCP whose cost is in the range, optionally only of one color, cheapest first
+    pub fn search_cost(&self, range: CostRange, color: Option<&str>) -> Option<Vec<&Arc<NCP>>> {
+        let mut found = self.search_any((range, color), |ncp, (range, color)| {
+            range.contains(ncp.e_b_cost) && color.is_none_or(|color| ncp.color.eq_ignore_ascii_case(color))
+        })?;
+        found.sort_by_key(|ncp| ncp.e_b_cost);
+        Some(found)
+    }
+
+    /// Every NCP whose description has all of the words searched for,
+    /// returned with its description with those words in bold
+    pub fn search_text(&self, text: &str) -> Vec<(&Arc<NCP>, String)> {
+        let words = text
+            .split_whitespace()
+            .map(|word| Regex::new(&format!("(?i){}", regex::escape(word))).expect("escaped words are valid regexes"))
+            .collect::<Vec<Regex>>();
+        if words.is_empty() {
+            return vec![];
+        }
+        let any_word = Regex::new(&format!(
+            "(?i){}",
+            text.split_whitespace().map(regex::escape).collect::<Vec<String>>().join("|")
+        ))
+        .expect("escaped words are valid regexes");
+
+        let mut found = self
+            .library
+            .values()
+            .filter(|ncp| words.iter().all(|word| word.is_match(&ncp.description)))
+            .map(|ncp| (ncp, any_word.replace_all(&ncp.description, "**$0**").into_owned()))
+            .collect::<Vec<(&Arc<NCP>, String)>>();
+        found.sort_unstable_by(|a, b| a.0.name.cmp(&b.0.name));
+        found
+    }
 
 }
 
@@ -204,7 +312,7 @@ pub(crate) fn ncp_as_lib_obj(obj: Arc<NCP>) -> Arc<dyn LibraryObject> {
 #[group]
 #[prefixes("n", "ncp")]
 #[default_command(send_ncp)]
-#[commands(send_ncp, send_ncp_color)]
+#[commands(send_ncp, send_ncp_color, send_ncp_cost, send_ncp_search)]
 /// A group of commands related to Navi-Customizer Parts, see `n ncp` for the get NCP command help
 struct BnbNcps;
 
@@ -221,7 +329,8 @@ pub(crate) async fn send_ncp(ctx: &Context, msg: &Message, args: Args) -> Comman
     let data = ctx.data.read().await;
     let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
     let library = library_lock.read().await;
-    library.reaction_name_search(ctx, msg, to_get).await;
+    let renderer = Renderer::new(&data, msg.author.id, msg.guild_id).await;
+    library.reaction_name_search(ctx, msg, to_get, &renderer).await;
     
     Ok(())
 }
@@ -255,3 +364,76 @@ pub(crate) async fn send_ncp_color(ctx: &Context, msg: &Message, args: Args) ->
     }
     Ok(())
 }
+
+#[command("cost")]
+#[aliases("eb")]
+/// get a list of NCPs which cost the given EB or a range of EB, optionally only of one color
+#[example = "2"]
+#[example = "0-2 pink"]
+pub(crate) async fn send_ncp_cost(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.is_empty() {
+        reply!(ctx, msg, "you must provide an EB cost, such as 2 or 1-3");
+        return Ok(());
+    }
+
+    let range = match args.single::<String>()?.parse::<CostRange>() {
+        Ok(range) => range,
+        Err(why) => {
+            reply!(ctx, msg, why);
+            return Ok(());
+        }
+    };
+
+    let color = args.current().map(str::to_lowercase);
+    if let Some(color) = &color {
+        if !COLORS.contains(&color.as_str()) {
+            reply!(ctx, msg, format!("{} is not a color\nValid colors are: `{:?}`", color, COLORS));
+            return Ok(());
+        }
+    }
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
+    let library = library_lock.read().await;
+    let found = match library.search_cost(range, color.as_deref()) {
+        Some(list) => list
+            .iter()
+            .map(|ncp| ncp.short_name())
+            .collect::<Vec<String>>(),
+        None => {
+            reply!(ctx, msg, format!("No NCPs cost {}", range));
+            return Ok(());
+        }
+    };
+    long_say!(ctx, msg, found, "\n");
+    Ok(())
+}
+
+#[command("search")]
+#[aliases("text")]
+/// get a list of NCPs whose description has all of the given words, with the words in bold
+#[example = "HP"]
+#[example = "max HP"]
+pub(crate) async fn send_ncp_search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let text = args.rest().trim();
+    if text.is_empty() {
+        reply!(ctx, msg, "you must provide some text to search for");
+        return Ok(());
+    }
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
+    let library = library_lock.read().await;
+    let found = library
+        .search_text(text)
+        .iter()
+        .map(|(ncp, highlighted)| format!("{}: {}", ncp.short_name(), highlighted))
+        .collect::<Vec<String>>();
+    if found.is_empty() {
+        reply!(ctx, msg, format!("No NCP descriptions mention {}", text));
+        return Ok(());
+    }
+
+    long_say!(ctx, msg, found, "\n");
+    Ok(())
+}
@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serenity::{
+    builder::CreateEmbed,
+    framework::standard::{macros::{command, group}, Args, CommandResult},
+    model::{
+        channel::Message,
+        id::{GuildId, UserId},
+    },
+    prelude::*,
+    utils::hashmap_to_json_map,
+};
+
+use std::collections::HashMap;
+
+use simple_error::SimpleError;
+
+use crate::{
+    library::{keywords::KeywordIndex, LibraryObject},
+    util::edit_message_by_id,
+};
+
+const OUTPUT_PREFS_FILE: &str = "./output_prefs.json";
+
+/// Discord will not show an embed field with an empty value
+pub(crate) fn embed_value(value: impl ToString) -> String {
+    let value = value.to_string();
+    if value.trim().is_empty() {
+        String::from("--")
+    } else {
+        value
+    }
+}
+
+/// How chips, NCPs, and viruses are shown when they are looked up
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputStyle {
+    #[default]
+    Text,
+    Embed,
+}
+
+impl std::str::FromStr for OutputStyle {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<OutputStyle, SimpleError> {
+        match to_parse.to_lowercase().as_str() {
+            "text" | "plain" => Ok(OutputStyle::Text),
+            "embed" | "embeds" => Ok(OutputStyle::Embed),
+            _ => Err(SimpleError::new(format!(
+                "{} is not an output style, use embed or text",
+                to_parse
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputStyle::Text => write!(f, "text"),
+            OutputStyle::Embed => write!(f, "embed"),
+        }
+    }
+}
+
+/// The output style each user and guild asked for, a user's choice wins over their guild's
+#[derive(Serialize, Deserialize, Default)]
+pub struct OutputPrefs {
+    #[serde(default)]
+    users: HashMap<u64, OutputStyle>,
+    #[serde(default)]
+    guilds: HashMap<u64, OutputStyle>,
+}
+
+impl OutputPrefs {
+    pub async fn import() -> RwLock<OutputPrefs> {
+        let prefs = match tokio::fs::read_to_string(OUTPUT_PREFS_FILE).await {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|why| {
+                eprintln!("Could not parse {}, starting with no output preferences: {}", OUTPUT_PREFS_FILE, why);
+                OutputPrefs::default()
+            }),
+            Err(_) => OutputPrefs::default(),
+        };
+        RwLock::new(prefs)
+    }
+
+    pub async fn save(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let json = serde_json::to_string(self)?;
+        tokio::fs::write(OUTPUT_PREFS_FILE, json).await?;
+        Ok(())
+    }
+
+    pub fn style(&self, user: UserId, guild: Option<GuildId>) -> OutputStyle {
+        self.users
+            .get(&user.0)
+            .or_else(|| guild.and_then(|guild| self.guilds.get(&guild.0)))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Set a user's style, or clear it when `None` so their guild's is used
+    fn set_user(&mut self, user: UserId, style: Option<OutputStyle>) {
+        match style {
+            Some(style) => self.users.insert(user.0, style),
+            None => self.users.remove(&user.0),
+        };
+    }
+
+    fn set_guild(&mut self, guild: GuildId, style: Option<OutputStyle>) {
+        match style {
+            Some(style) => self.guilds.insert(guild.0, style),
+            None => self.guilds.remove(&guild.0),
+        };
+    }
+}
+
+impl TypeMapKey for OutputPrefs {
+    type Value = RwLock<OutputPrefs>;
+}
+
+/// Formats library objects in the style the user asked for, with their related keywords
+///
+/// The keyword index is only read while an object is being formatted, never held between lookups,
+/// so waiting on a "did you mean" reaction doesn't hold up a reload.
+pub struct Renderer<'a> {
+    data: &'a TypeMap,
+    style: OutputStyle,
+}
+
+impl<'a> Renderer<'a> {
+    pub async fn new(data: &'a TypeMap, user: UserId, guild: Option<GuildId>) -> Renderer<'a> {
+        let style = data
+            .get::<OutputPrefs>()
+            .expect("output preferences not found")
+            .read()
+            .await
+            .style(user, guild);
+        Renderer { data, style }
+    }
+
+    /// The statuses, panels, and blights an object mentions
+    async fn related<T: LibraryObject + ?Sized>(&self, obj: &T) -> Option<String> {
+        self.data
+            .get::<KeywordIndex>()
+            .expect("keyword index not found")
+            .read()
+            .await
+            .related(obj)
+    }
+
+    fn text<T: LibraryObject + ?Sized>(obj: &T, related: Option<String>, note: Option<&str>) -> String {
+        let footer = related.map(|related| format!("\nRelated: {}", related)).unwrap_or_default();
+        match note {
+            Some(note) => format!("*{}*\n{}{}", note, obj, footer),
+            None => format!("{}{}", obj, footer),
+        }
+    }
+
+    fn embed<T: LibraryObject + ?Sized>(obj: &T, related: Option<String>, note: Option<&str>) -> CreateEmbed {
+        let mut embed = obj.embed();
+        if let Some(related) = related {
+            embed.field("Related", related, false);
+        }
+        if let Some(note) = note {
+            embed.footer(|f| f.text(note));
+        }
+        embed
+    }
+
+    pub async fn reply<T: LibraryObject + ?Sized>(&self, ctx: &Context, msg: &Message, obj: &T) -> serenity::Result<Message> {
+        self.reply_noted(ctx, msg, obj, None).await
+    }
+
+    /// Reply with an object and a short note about where it came from, such as a homebrew chip
+    pub async fn reply_noted<T: LibraryObject + ?Sized>(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        obj: &T,
+        note: Option<&str>,
+    ) -> serenity::Result<Message> {
+        let related = self.related(obj).await;
+        match self.style {
+            OutputStyle::Text => msg.reply(ctx, Renderer::text(obj, related, note)).await,
+            OutputStyle::Embed => {
+                let embed = Renderer::embed(obj, related, note);
+                msg.channel_id
+                    .send_message(ctx, |m| m.set_embed(embed).reference_message(msg).allowed_mentions(|a| a.replied_user(false)))
+                    .await
+            }
+        }
+    }
+
+    /// Replace a message, such as a "did you mean" prompt, with an object
+    pub async fn edit<T: LibraryObject + ?Sized>(&self, ctx: &Context, to_edit: &Message, obj: &T) -> serenity::Result<Message> {
+        let related = self.related(obj).await;
+        match self.style {
+            OutputStyle::Text => {
+                edit_message_by_id(ctx, to_edit.channel_id, to_edit.id, Renderer::text(obj, related, None)).await
+            }
+            OutputStyle::Embed => {
+                let embed = Renderer::embed(obj, related, None);
+                to_edit.channel_id.edit_message(ctx, to_edit.id, |e| e.content("").set_embed(embed)).await
+            }
+        }
+    }
+
+    /// The data of a slash command response showing an object
+    pub async fn interaction_data<T: LibraryObject + ?Sized>(&self, obj: &T) -> serde_json::Value {
+        let related = self.related(obj).await;
+        match self.style {
+            OutputStyle::Text => json!({ "content": Renderer::text(obj, related, None) }),
+            OutputStyle::Embed => json!({ "embeds": [hashmap_to_json_map(Renderer::embed(obj, related, None).0)] }),
+        }
+    }
+}
+
+#[group]
+#[prefixes("output")]
+#[default_command(show_output)]
+#[commands(show_output, set_user_output, set_guild_output)]
+/// Choose whether chips, NCPs, and viruses are shown as embeds or plain text
+struct Output;
+
+#[command("show")]
+/// Show which output style your lookups use
+async fn show_output(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let data = ctx.data.read().await;
+    let prefs = data.get::<OutputPrefs>().expect("output preferences not found").read().await;
+
+    let mut to_send = format!("Your lookups are shown as {}", prefs.style(msg.author.id, msg.guild_id));
+    if let Some(style) = prefs.users.get(&msg.author.id.0) {
+        to_send.push_str(&format!("\nYou chose {}", style));
+    }
+    if let Some(style) = msg.guild_id.and_then(|guild| prefs.guilds.get(&guild.0)) {
+        to_send.push_str(&format!("\nThis server uses {} by default", style));
+    }
+    reply!(ctx, msg, to_send);
+    Ok(())
+}
+
+/// Parse a style argument, where "default" clears the preference
+fn parse_style(args: &Args) -> Result<Option<OutputStyle>, SimpleError> {
+    match args.rest().trim().to_lowercase().as_str() {
+        "default" | "clear" => Ok(None),
+        style => style.parse().map(Some),
+    }
+}
+
+#[command("me")]
+/// Set the output style for your own lookups, or "default" to use the server's
+#[example = "embed"]
+async fn set_user_output(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let style = match parse_style(&args) {
+        Ok(style) => style,
+        Err(why) => {
+            reply!(ctx, msg, why.as_str());
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let mut prefs = data.get::<OutputPrefs>().expect("output preferences not found").write().await;
+    prefs.set_user(msg.author.id, style);
+    if let Err(why) = prefs.save().await {
+        eprintln!("Could not save output preferences: {:?}", why);
+    }
+
+    match style {
+        Some(style) => reply!(ctx, msg, format!("Your lookups will now be shown as {}", style)),
+        None => reply!(ctx, msg, "Your lookups will now use the server's style"),
+    }
+    Ok(())
+}
+
+#[command("server")]
+#[aliases("guild")]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+/// Set the output style for everyone in this server who has not chosen their own
+#[example = "text"]
+async fn set_guild_output(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let style = match parse_style(&args) {
+        Ok(style) => style,
+        Err(why) => {
+            reply!(ctx, msg, why.as_str());
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let mut prefs = data.get::<OutputPrefs>().expect("output preferences not found").write().await;
+    prefs.set_guild(guild, style);
+    if let Err(why) = prefs.save().await {
+        eprintln!("Could not save output preferences: {:?}", why);
+    }
+
+    reply!(ctx, msg, format!("Lookups in this server will now be shown as {}", style.unwrap_or_default()));
+    Ok(())
+}
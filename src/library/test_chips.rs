@@ -0,0 +1,31 @@
+//! A handful of chips shared by the tests which need a library to work with
+
+use crate::library::{battlechip::BattleChip, chip_library::{parse_chip_text, ChipLibrary}};
+
+/// Chips covering a few elements, skills, ranges, classes, and hit counts, including one which deals no damage
+pub(crate) const TEST_CHIPS: &str = "\
+Cannon - Null | Per | Far | 2d6 damage | | 1 hit | Projectile
+A simple cannon shot.
+AirShot - Wind | Per | Far | 1d6 damage | | 1 hit | Projectile
+A puff of air which pushes the target back.
+FireSword - Fire | Str | Close | 3d6 damage | | 1 hit | Melee
+A burning slash.
+AquaTower - Aqua | Per | Near | 2d6 damage | Mega | 1-3 hits | Wave
+A pillar of water which travels along the ground.
+Recover - Null | None | Self | -- | | -- hits | Recovery
+Heals the user.
+WideSword - Sword | Str | Close | 3d6 damage | | 1 hit | Melee
+A wide slash in front of the user.
+";
+
+/// The test chips, in the order they are written
+pub(crate) fn chips() -> Vec<BattleChip> {
+    let (chips, diagnostics) = parse_chip_text(TEST_CHIPS, "test");
+    assert!(diagnostics.is_empty(), "the test chips did not all parse");
+    chips
+}
+
+/// A library holding only the test chips
+pub(crate) fn library() -> ChipLibrary {
+    ChipLibrary::from_chip_text(TEST_CHIPS)
+}
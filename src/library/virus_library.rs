@@ -8,6 +8,7 @@ use rand::distributions::{Distribution, Uniform};
 use rand::rngs::ThreadRng;
 
 use serenity::{
+    builder::CreateEmbed,
     framework::standard::{macros::{command, group}, Args, CommandResult},
     model::channel::Message,
     prelude::*,
@@ -21,7 +22,8 @@ use serde_json;
 use regex::Regex;
 
 use crate::{
-    library::{battlechip::skills::Skills, elements::Elements, Library, LibraryObject},
+    dice::{DiceError, DieRoll},
+    library::{battlechip::skills::{Attributes, Skills}, elements::Elements, render::{embed_value, Renderer}, Library, LibraryObject},
     ReloadReturnType,
 };
 use simple_error::SimpleError;
@@ -99,6 +101,77 @@ impl std::fmt::Display for VirusDrops {
     }
 }
 
+/// A check a virus can be asked to roll, either a skill or a bare attribute
+#[derive(Clone, Copy)]
+pub enum VirusCheck {
+    Skill(Skills),
+    Attribute(Attributes),
+}
+
+impl std::str::FromStr for VirusCheck {
+    type Err = SimpleError;
+
+    fn from_str(to_parse: &str) -> Result<VirusCheck, SimpleError> {
+        if let Ok(attribute) = Attributes::from_str(to_parse) {
+            return Ok(VirusCheck::Attribute(attribute));
+        }
+
+        match Skills::from_str(to_parse) {
+            Ok(skill) if skill.attribute().is_some() => Ok(VirusCheck::Skill(skill)),
+            _ => Err(SimpleError::new("could not parse skill or attribute")),
+        }
+    }
+}
+
+impl std::fmt::Display for VirusCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VirusCheck::Skill(skill) => write!(f, "{}", skill),
+            VirusCheck::Attribute(attribute) => write!(f, "{}", attribute),
+        }
+    }
+}
+
+impl Virus {
+    pub fn attribute(&self, attribute: Attributes) -> u8 {
+        match attribute {
+            Attributes::Mind => self.mind,
+            Attributes::Body => self.body,
+            Attributes::Spirit => self.spirit,
+        }
+    }
+
+    /// Viruses without a listed skill have a value of 0 in it
+    pub fn skill(&self, skill: Skills) -> u8 {
+        self.skills.0.get(&skill).copied().unwrap_or(0)
+    }
+
+    /// The dice rolled when this virus makes the given check,
+    /// one d6 per point in the attribute plus the value of the skill
+    pub fn check_expr(&self, check: VirusCheck) -> String {
+        let (attribute, bonus) = match check {
+            VirusCheck::Skill(skill) => (
+                skill.attribute().expect("checks are only made with attribute skills"),
+                self.skill(skill),
+            ),
+            VirusCheck::Attribute(attribute) => (attribute, 0),
+        };
+
+        match (self.attribute(attribute), bonus) {
+            (0, bonus) => bonus.to_string(),
+            (dice, 0) => format!("{}d6", dice),
+            (dice, bonus) => format!("{}d6 + {}", dice, bonus),
+        }
+    }
+
+    /// Roll the given check for this virus, returning a line describing the result
+    pub fn roll_check(&self, check: VirusCheck) -> Result<String, DiceError> {
+        let expr = self.check_expr(check);
+        let res = DieRoll::roll_dice(&expr, false)?;
+        Ok(format!("{} - {} ({}): {} {}", self.name, check, expr, res.total, res.breakdown))
+    }
+}
+
 impl LibraryObject for Virus {
     #[inline]
     fn get_name(&self) -> &str {
@@ -109,6 +182,28 @@ impl LibraryObject for Virus {
         "Virus"
     }
 
+    fn embed(&self) -> CreateEmbed {
+        let abilities = match &self.abilities {
+            Some(abilities) => abilities.join(", "),
+            None => String::from("None"),
+        };
+
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(&self.name)
+            .description(embed_value(&self.description))
+            .field("Element", embed_value(self.element.iter().format(", ")), true)
+            .field("CR", self.c_r, true)
+            .field("HP / AC", format!("{} / {}", self.h_p, self.a_c), true)
+            .field("Mind / Body / Spirit", format!("{} / {} / {}", self.mind, self.body, self.spirit), true)
+            .field("Skills", embed_value(&self.skills), true)
+            .field("Abilities", embed_value(abilities), false)
+            .field("Drops", embed_value(&self.drops), false);
+        if let Some(element) = self.element.first() {
+            embed.colour(element.colour());
+        }
+        embed
+    }
 }
 
 impl std::fmt::Display for Virus {
@@ -681,7 +776,9 @@ impl VirusLibrary {
     send_virus_element,
     send_virus_cr,
     send_random_encounter,
-    send_family
+    send_family,
+    roll_virus_check,
+    roll_virus_check_all
 )]
 /// A group of commands related to viruses, see `v virus` for the get virus command help
 struct BnbViruses;
@@ -699,7 +796,8 @@ pub(crate) async fn send_virus(ctx: &Context, msg: &Message, args: Args) -> Comm
     let library_lock = data.get::<VirusLibrary>().expect("Virus library not found");
     let library = library_lock.read().await;
     //.expect("library was poisoned, panicking");
-    library.reaction_name_search(ctx, msg, to_search).await;
+    let renderer = Renderer::new(&data, msg.author.id, msg.guild_id).await;
+    library.reaction_name_search(ctx, msg, to_search, &renderer).await;
     // say!(ctx, msg, search_lib_obj(&to_search, library));
     Ok(())
 }
@@ -871,3 +969,83 @@ pub(crate) async fn send_family(ctx: &Context, msg: &Message, args: Args) -> Com
     }
     Ok(())
 }
+
+#[command("roll")]
+/// Roll a skill or attribute check for a virus, rolling a d6 per point in the attribute and adding the skill
+#[example = "Mettaur Agility"]
+#[example = "Swordy str"]
+#[example = "Canodumb Mind"]
+pub(crate) async fn roll_virus_check(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let mut words = args.raw().collect::<Vec<&str>>();
+    let check_str = match words.pop() {
+        Some(check) if !words.is_empty() => check,
+        _ => {
+            reply!(ctx, msg, "you must provide a virus name and a skill or attribute");
+            return Ok(());
+        }
+    };
+
+    let check = match VirusCheck::from_str(check_str) {
+        Ok(check) => check,
+        Err(_) => {
+            reply!(ctx, msg, format!("{} is not a skill or attribute, perhaps you spelled it wrong?", check_str));
+            return Ok(());
+        }
+    };
+
+    let to_search = words.join(" ");
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<VirusLibrary>().expect("Virus library not found");
+    let library = library_lock.read().await;
+
+    let virus = match library.search_lib_obj(&to_search) {
+        Ok(virus) => virus,
+        Err(viruses) => {
+            let to_say = viruses.iter().map(|a| a.get_name()).collect::<Vec<&str>>().join(", ");
+            reply!(ctx, msg, format!("Did you mean: {}", to_say));
+            return Ok(());
+        }
+    };
+
+    reply!(ctx, msg, virus.roll_check(check)?);
+    Ok(())
+}
+
+#[command("rollall")]
+/// Roll the same skill or attribute check for every virus in a comma separated list, such as the result of the encounter command
+#[example = "Agility Mettaur, Mettaur, Canodumb"]
+pub(crate) async fn roll_virus_check_all(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.len() < 2 {
+        reply!(ctx, msg, "you must provide a skill or attribute and a list of viruses");
+        return Ok(());
+    }
+
+    let check_str = args.single::<String>()?;
+    let check = match VirusCheck::from_str(&check_str) {
+        Ok(check) => check,
+        Err(_) => {
+            reply!(ctx, msg, format!("{} is not a skill or attribute, perhaps you spelled it wrong?", check_str));
+            return Ok(());
+        }
+    };
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<VirusLibrary>().expect("Virus library not found");
+    let library = library_lock.read().await;
+
+    let mut results = vec![];
+    for name in args.rest().split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let line = match library.search_lib_obj(name) {
+            Ok(virus) => virus.roll_check(check)?,
+            Err(viruses) => format!(
+                "{} - no virus found, did you mean: {}",
+                name,
+                viruses.iter().map(|a| a.get_name()).join(", ")
+            ),
+        };
+        results.push(line);
+    }
+
+    long_say!(ctx, msg, results, "\n");
+    Ok(())
+}
@@ -24,15 +24,27 @@ use serenity::{
 
 use crate::{
     bot_data::BotData,
-    dice::DICE_GROUP,
+    dice::{
+        gm::{GmConfig, GM_GROUP},
+        history::{RollHistory, ROLLS_GROUP},
+        roll_macros::{RollMacros, MACROS_GROUP},
+        DICE_GROUP,
+    },
+    initiative::{InitiativeTrackers, INITIATIVE_GROUP},
     handler::Handler,
     library::{
         blights::{
             Blights, Panels, Statuses, GET_BLIGHT_COMMAND, GET_PANELS_COMMAND, GET_STATUS_COMMAND,
         },
+        changelog::{record_changes, Changelog, Snapshots, CHANGES_COMMAND},
         chip_library::{battlechip_as_lib_obj, ChipLibrary, BNBCHIPS_GROUP, BNBSKILLS_GROUP},
+        folder::{Folders, FOLDER_GROUP},
+        homebrew::{HomebrewChips, HOMEBREW_GROUP},
+        keywords::{rebuild_keywords, KeywordIndex},
         full_library::{check_virus_abilities, check_virus_drops, search_full_library, FullLibrary, CHIP_DROP_COMMAND},
+        navicust::{NaviCusts, NAVICUSTOMIZER_GROUP},
         ncp_library::{ncp_as_lib_obj, NCPLibrary, BNBNCPS_GROUP},
+        render::{OutputPrefs, OUTPUT_GROUP},
         virus_library::{virus_as_lib_obj, VirusLibrary, BNBVIRUSES_GROUP},
         Library, LibraryObject,
     },
@@ -44,6 +56,7 @@ use crate::{
 mod util;
 mod bot_data;
 mod dice;
+mod initiative;
 mod library;
 mod handler;
 mod slash_cmds;
@@ -89,7 +102,7 @@ struct Owner;
 
 #[group]
 #[commands(
-    manager, phb, reload, get_blight, about_bot, chip_drop, get_status, get_panels, ping, groups
+    manager, phb, reload, changes, get_blight, about_bot, chip_drop, get_status, get_panels, ping, groups
 )]
 /// Misc. commands related to BnB
 struct BnbGeneral;
@@ -127,6 +140,8 @@ async fn reload(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
         return Ok(());
     }
 
+    let before = Snapshots::take(&*ctx.data.read().await).await;
+
     let chip_data = Arc::clone(&ctx.data);
     let ncp_data = Arc::clone(&ctx.data);
     let virus_data = Arc::clone(&ctx.data);
@@ -193,6 +208,10 @@ async fn reload(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
         format!("{}{}{}\n{}", chip_res.0, ncp_res.0, virus_res.0, blight_string)
     };
 
+    let changes = record_changes(ctx, &data, before).await;
+    str_to_send.push('\n');
+    str_to_send.push_str(&changes);
+
     let virus_lib_lock = data.get::<VirusLibrary>().expect("virus library not found");
     let chip_lib_lock = data.get::<ChipLibrary>().expect("chip library not found");
     let ncp_lib_lock = data.get::<NCPLibrary>().expect("ncp library not found");
@@ -210,7 +229,52 @@ async fn reload(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
         str_to_send.push_str(why.as_str());
     }
 
+    rebuild_keywords(&data, &chip_lib, &ncp_lib, &virus_lib).await;
+
+    let folders_lock = data.get::<Folders>().expect("folders not found");
+    let mut folders = folders_lock.write().await;
+    let flagged = folders.flag_missing(&chip_lib);
+    if let Err(why) = folders.save().await {
+        eprintln!("Could not save folders: {:?}", why);
+    }
+    if flagged > 0 {
+        str_to_send.push_str(&format!("\n{} folders have chips no longer in the library", flagged));
+    }
+
+    let navicusts_lock = data.get::<NaviCusts>().expect("navicusts not found");
+    let mut navicusts = navicusts_lock.write().await;
+    let flagged = navicusts.flag_missing(&ncp_lib);
+    if let Err(why) = navicusts.save().await {
+        eprintln!("Could not save navicusts: {:?}", why);
+    }
+    if flagged > 0 {
+        str_to_send.push_str(&format!("\n{} Navi-Cust loadouts have parts no longer in the library", flagged));
+    }
+
+    let homebrew_lock = data.get::<HomebrewChips>().expect("homebrew not found");
+    let shadowing = homebrew_lock.write().await.rebuild(&chip_lib);
+    if shadowing > 0 {
+        str_to_send.push_str(&format!(
+            "\n{} servers have homebrew chips with the same name as an official chip",
+            shadowing
+        ));
+    }
+
     say!(ctx, msg, str_to_send);
+
+    let diagnostics = chip_lib.diagnostics();
+    if !diagnostics.is_empty() {
+        let report = diagnostics.iter().map(|diag| diag.to_string()).collect::<Vec<String>>().join("\n");
+        if let Err(why) = msg
+            .channel_id
+            .send_files(&ctx.http, vec![(report.as_bytes(), "chip_report.txt")], |m| {
+                m.content("Chip entries which could not be parsed:")
+            })
+            .await
+        {
+            println!("Could not send chip report: {:?}", why);
+        }
+    }
     Ok(())
 }
 
@@ -289,6 +353,12 @@ async fn main() {
     let blights_future = Blights::import();
     let statuses_future = Statuses::import();
     let panels_future = Panels::import();
+    let macros = RollMacros::import().await;
+    let gm_config = GmConfig::import().await;
+    let folders = Folders::import().await;
+    let navicusts = NaviCusts::import().await;
+    let changelog = Changelog::import().await;
+    let output_prefs = OutputPrefs::import().await;
 
     // join all three futures at once, panic if any one returns an error, unrecoverable
     let (blights, statuses, panels) =
@@ -312,6 +382,10 @@ async fn main() {
     // panic if chip or ncp librarys are unusable
     let chip_count = chip_res.unwrap();
     println!("{} chips were loaded", chip_count);
+    for diag in chip_library.diagnostics() {
+        println!("skipped invalid chip, {}", diag);
+    }
+    let homebrew = HomebrewChips::import(&chip_library).await;
     let ncp_count = ncp_res.unwrap();
     println!("{} programs loaded", ncp_count);
 
@@ -353,6 +427,15 @@ async fn main() {
 
     println!("Full library loaded, size is {}", full_library.len());
 
+    let keywords = KeywordIndex::build(
+        &*statuses.read().await,
+        &*panels.read().await,
+        &*blights.read().await,
+        &chip_library,
+        &ncp_library,
+        &virus_library,
+    );
+
     // configure standard framework
     let mut owners = std::collections::HashSet::new();
     let owner_id = serenity::model::id::UserId(config.owner);
@@ -372,11 +455,19 @@ async fn main() {
         .help(&HELP_COMMAND)
         .group(&OWNER_GROUP)
         .group(&DICE_GROUP)
+        .group(&MACROS_GROUP)
+        .group(&ROLLS_GROUP)
+        .group(&GM_GROUP)
+        .group(&INITIATIVE_GROUP)
         .group(&BNBGENERAL_GROUP)
         .group(&BNBCHIPS_GROUP)
         .group(&BNBSKILLS_GROUP)
+        .group(&FOLDER_GROUP)
+        .group(&HOMEBREW_GROUP)
+        .group(&OUTPUT_GROUP)
         .group(&BNBVIRUSES_GROUP)
-        .group(&BNBNCPS_GROUP);
+        .group(&BNBNCPS_GROUP)
+        .group(&NAVICUSTOMIZER_GROUP);
 
     let mut client = Client::builder(&config.token)
         .event_handler(Handler)
@@ -402,6 +493,16 @@ async fn main() {
         data.insert::<Blights>(blights);
         data.insert::<Statuses>(statuses);
         data.insert::<Panels>(panels);
+        data.insert::<KeywordIndex>(RwLock::new(keywords));
+        data.insert::<RollMacros>(macros);
+        data.insert::<GmConfig>(gm_config);
+        data.insert::<Folders>(folders);
+        data.insert::<NaviCusts>(navicusts);
+        data.insert::<Changelog>(changelog);
+        data.insert::<HomebrewChips>(homebrew);
+        data.insert::<OutputPrefs>(output_prefs);
+        data.insert::<RollHistory>(RwLock::new(RollHistory::default()));
+        data.insert::<InitiativeTrackers>(RwLock::new(Default::default()));
         data.insert::<ShardManagerContainer>(Arc::clone(&client.shard_manager));
     }
 
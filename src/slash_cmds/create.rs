@@ -11,11 +11,14 @@ use serenity::{
 
 use serde_json::json;
 
-use crate::library::blights::{
-    Panels,
-    Blights,
-    Statuses,
-    StatusLike,
+use crate::library::{
+    blights::{
+        Panels,
+        Blights,
+        Statuses,
+        StatusLike,
+    },
+    ncp_library::COLORS,
 };
 
 #[cfg(debug_assertions)]
@@ -50,6 +53,16 @@ async fn create_commands(ctx: &Context, msg: &Message, _: Args) -> CommandResult
         return Ok(());
     }
 
+    if let Err(why) = create_chip_cmd(ctx).await {
+        eprintln!("Error creating chip cmd, {:?}", why);
+        return Ok(());
+    }
+
+    if let Err(why) = create_ncp_cmd(ctx).await {
+        eprintln!("Error creating ncp cmd, {:?}", why);
+        return Ok(());
+    }
+
     msg.react(ctx, '\u{1f44d}').await?;
 
     Ok(())
@@ -97,14 +110,83 @@ async fn create_roll_cmd(ctx: &Context) -> Result<(), serenity::Error> {
     
     let payload = json!({
         "name": "roll",
-        "description": "Roll XdY dice, 1d20 by default",
+        "description": "Roll dice, either adding them up or counting successes",
         "options": [
             {
-            "name": "dice",
-            "description": "Must be in the format XdY with optional modifiers",
-            "type": 3,
-            "required": false,
-        },
+                "name": "dice",
+                "description": "Roll XdY dice or one of your saved macros and add them up, 1d20 by default",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "dice",
+                    "description": "In the format XdY, may use + - * / ( ), khN/klN/dhN/dlN to keep or drop, and ! to explode",
+                    "type": 3,
+                    "required": false,
+                },
+                    {
+                    "name": "hidden",
+                    "description": "DM the result to you and the channel's GM instead of posting it",
+                    "type": 5,
+                    "required": false,
+                },
+                ],
+            },
+            {
+                "name": "pool",
+                "description": "Roll a pool of dice and count how many hit the target number",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "dice",
+                    "description": "In the format XdY, may use ! to add a die on each max roll, 6d6 rolls six d6s",
+                    "type": 3,
+                    "required": true,
+                },
+                    {
+                    "name": "target",
+                    "description": "The lowest roll which counts as a success, 5 by default",
+                    "type": 4,
+                    "required": false,
+                },
+                    {
+                    "name": "extra",
+                    "description": "Extra dice to add to the pool",
+                    "type": 4,
+                    "required": false,
+                },
+                    {
+                    "name": "hidden",
+                    "description": "DM the result to you and the channel's GM instead of posting it",
+                    "type": 5,
+                    "required": false,
+                },
+                ],
+            },
+            {
+                "name": "stats",
+                "description": "Generate character stats, 4d6 drop the lowest by default",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "method",
+                    "description": "How to generate the stats",
+                    "type": 3,
+                    "required": false,
+                    "choices": [
+                        { "name": "4d6 drop the lowest", "value": "4d6" },
+                        { "name": "Standard array", "value": "array" },
+                        { "name": "Point buy", "value": "pointbuy" },
+                        { "name": "BnB attributes", "value": "bnb" },
+                    ],
+                },
+                    {
+                    "name": "values",
+                    "description": "Scores to check for point buy, or Mind Body Spirit for bnb",
+                    "type": 3,
+                    "required": false,
+                },
+                ],
+            },
         ],
     });
     
@@ -223,4 +305,160 @@ async fn create_blight_command(ctx: &Context) -> Result<(), serenity::Error> {
 
     Ok(())
 
-}
\ No newline at end of file
+}
+async fn create_chip_cmd(ctx: &Context) -> Result<(), serenity::Error> {
+
+    let choices = |names: &[&str]| {
+        names.iter().map(|name| json!({ "name": name, "value": name })).collect::<Vec<_>>()
+    };
+
+    let elements = json!({
+        "name": "element",
+        "description": "Only list chips of this element",
+        "type": 3,
+        "required": false,
+        "choices": choices(&[
+            "Fire", "Aqua", "Elec", "Wood", "Wind", "Sword", "Break", "Cursor", "Recovery", "Invis", "Object", "Null"
+        ]),
+    });
+
+    let payload = json!({
+        "name": "chip",
+        "description": "Look up a chip, or list chips by their class, type, or range",
+        "options": [
+            {
+                "name": "get",
+                "description": "Look up a chip by name",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "get",
+                    "description": "The name of the chip",
+                    "type": 3,
+                    "required": true,
+                },
+                ],
+            },
+            {
+                "name": "class",
+                "description": "List all chips of a class",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "class",
+                    "description": "The class of chip to list",
+                    "type": 3,
+                    "required": true,
+                    "choices": choices(&["Standard", "Mega", "Giga", "Dark", "Support"]),
+                },
+                    elements,
+                ],
+            },
+            {
+                "name": "type",
+                "description": "List all chips of a type",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "type",
+                    "description": "The type of chip to list",
+                    "type": 3,
+                    "required": true,
+                    "choices": choices(&[
+                        "Burst", "Construct", "Melee", "Projectile", "Wave", "Recovery", "Summon", "Support", "Trap"
+                    ]),
+                },
+                    elements,
+                ],
+            },
+            {
+                "name": "range",
+                "description": "List all chips with a range",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "range",
+                    "description": "The range of chip to list",
+                    "type": 3,
+                    "required": true,
+                    "choices": choices(&["Self", "Close", "Near", "Far", "Varies"]),
+                },
+                    elements,
+                ],
+            },
+        ],
+    });
+
+    #[cfg(debug_assertions)]
+    {
+        let data = ctx.data.read().await;
+        let config = data.get::<BotData>().expect("No bot data available");
+        let guild_id = config.primary_guild;
+        ctx.http.create_guild_application_command(guild_id, &payload).await?;
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        ctx.http.create_global_application_command(&payload).await?;
+    }
+
+    Ok(())
+}
+
+async fn create_ncp_cmd(ctx: &Context) -> Result<(), serenity::Error> {
+
+    let colors = COLORS.iter().map(|color| json!({ "name": color, "value": color })).collect::<Vec<_>>();
+
+    let payload = json!({
+        "name": "ncp",
+        "description": "Search Navi-Customizer parts by their cost or description",
+        "options": [
+            {
+                "name": "cost",
+                "description": "List all parts which cost an amount of EB",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "cost",
+                    "description": "The EB cost, or a range such as 1-3",
+                    "type": 3,
+                    "required": true,
+                },
+                    {
+                    "name": "color",
+                    "description": "Only list parts of this color",
+                    "type": 3,
+                    "required": false,
+                    "choices": colors,
+                },
+                ],
+            },
+            {
+                "name": "search",
+                "description": "List all parts whose description has every word given",
+                "type": 1,
+                "options": [
+                    {
+                    "name": "search",
+                    "description": "The words to search for",
+                    "type": 3,
+                    "required": true,
+                },
+                ],
+            },
+        ],
+    });
+
+    #[cfg(debug_assertions)]
+    {
+        let data = ctx.data.read().await;
+        let config = data.get::<BotData>().expect("No bot data available");
+        let guild_id = config.primary_guild;
+        ctx.http.create_guild_application_command(guild_id, &payload).await?;
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        ctx.http.create_global_application_command(&payload).await?;
+    }
+
+    Ok(())
+}
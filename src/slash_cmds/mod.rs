@@ -5,12 +5,26 @@ use serenity::{
 };
 
 use crate::{
-    library::blights::{
-        Panels,
-        Blights,
-        Statuses,
+    library::{
+        blights::{
+            Panels,
+            Blights,
+            Statuses,
+        },
+        chip_library::ChipLibrary,
+        elements::Elements,
+        ncp_library::{CostRange, NCPLibrary},
+        render::Renderer,
+        Library,
+        LibraryObject,
+    },
+    dice::{
+        gm::deliver_secret_roll,
+        history::{roll_and_record, RollKind},
+        roll_macros::expand_macro,
+        stats::generate_stats,
+        DieRoll,
     },
-    dice::DieRoll,
 };
 
 pub(crate) mod create;
@@ -42,7 +56,9 @@ async fn handle_command_call(ctx: &Context, interaction: &Interaction, data: &Ap
 
         "blight" => blight_command(ctx, data).await,
         "panels" => panel_command(ctx, data).await,
-        "roll" => roll_command(data).await,
+        "roll" => roll_command(ctx, interaction, data).await,
+        "chip" => chip_command(ctx, interaction, data).await,
+        "ncp" => ncp_command(ctx, data).await,
         "shuffle" => shuffle_command(data).await,
         "status" => status_command(ctx, data).await,
         _ => {
@@ -57,7 +73,10 @@ async fn handle_command_call(ctx: &Context, interaction: &Interaction, data: &Ap
 
     };
 
-    ctx.http.create_interaction_response(interaction.id.0, &interaction.token, &resp).await?;
+    // a command which has already answered, such as a deferred hidden roll, has nothing left to send
+    if !resp.is_null() {
+        ctx.http.create_interaction_response(interaction.id.0, &interaction.token, &resp).await?;
+    }
 
     Ok(())
 }
@@ -99,57 +118,251 @@ async fn panel_command(ctx: &Context, data: &ApplicationCommandInteractionData)
     })
 }
 
-async fn roll_command(data: &ApplicationCommandInteractionData) -> serde_json::Value {
-    let to_roll = data.options.get(0).and_then(|d| d.value.as_ref()).and_then(|o| o.as_str()).unwrap_or("1d20");
-    let owned_to_roll = to_roll.to_owned();
-    let result = tokio::task::spawn_blocking(move || {
-        let (amt, results) = DieRoll::roll_dice(&owned_to_roll, false)?;
-
-        let repl_str = format!("{:?}", results);
-        let reply = if repl_str.len() > 1850 {
-            format!(
-                "You rolled: {}\n[There were too many die rolls to show the result of each one]",
-                amt
-            )
-        } else {
-            format!(
-                "You rolled: {}\n{}",
-                amt,
-                repl_str
-            )
-        };
+/// Get the value of the option with the given name, if it was provided
+fn get_option<'a>(options: &'a [ApplicationCommandInteractionDataOption], name: &str) -> Option<&'a serde_json::Value> {
+    options.iter().find(|o| o.name == name).and_then(|o| o.value.as_ref())
+}
+
+async fn roll_command(ctx: &Context, interaction: &Interaction, data: &ApplicationCommandInteractionData) -> serde_json::Value {
+    let subcommand = match data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            return json!({
+                "type": 4,
+                "data": {
+                    "content": "No roll type was provided, inform Major"
+                }
+            });
+        }
+    };
 
-        Some(json!({
+    let to_roll = get_option(&subcommand.options, "dice").and_then(|o| o.as_str()).unwrap_or("1d20");
+    let user = interaction.member.as_ref().map(|m| &m.user).or(interaction.user.as_ref());
+    let (user, channel) = match (user, interaction.channel_id) {
+        (Some(user), Some(channel)) => (user, channel),
+        _ => {
+            return json!({
+                "type": 4,
+                "data": {
+                    "content": "Could not tell who rolled or where, inform Major"
+                }
+            });
+        }
+    };
+
+    if subcommand.name == "stats" {
+        let method = get_option(&subcommand.options, "method").and_then(|o| o.as_str());
+        let values = get_option(&subcommand.options, "values").and_then(|o| o.as_str()).unwrap_or_default();
+        return json!({
             "type": 4,
             "data": {
-                "content": reply
+                "content": generate_stats(ctx, user, channel, method, values).await
             }
-        }))
+        });
+    }
 
-    }).await;
+    let owned_to_roll = expand_macro(ctx, user.id, to_roll).await;
+    let hidden = get_option(&subcommand.options, "hidden").and_then(|o| o.as_bool()).unwrap_or(false);
+
+    let (to_roll, kind) = match subcommand.name.as_str() {
+        "pool" => {
+            let target = get_option(&subcommand.options, "target").and_then(|o| o.as_i64());
+            let extra = get_option(&subcommand.options, "extra").and_then(|o| o.as_i64());
+            match DieRoll::build_pool(&owned_to_roll, target, extra) {
+                Ok(pool) => (pool, RollKind::Pool),
+                Err(why) => {
+                    return json!({
+                        "type": 4,
+                        "data": {
+                            "content": why.to_string()
+                        }
+                    });
+                }
+            }
+        }
+        _ => (owned_to_roll, RollKind::Roll),
+    };
 
-    match result {
-        Ok(Some(val)) => val,
-        Ok(None) => {
-            eprintln!("Too man die rolls: {}", to_roll);
-            json!({
+    // DMing the roller and every GM can take longer than discord waits for a response, so answer first
+    if hidden {
+        let deferred = json!({
+            "type": 5,
+            "data": {
+                "flags": 64
+            }
+        });
+        if let Err(why) = ctx.http.create_interaction_response(interaction.id.0, &interaction.token, &deferred).await {
+            eprintln!("Error deferring a hidden roll, {:?}", why);
+        }
+    }
+
+    let result = roll_and_record(ctx, user, channel, kind, to_roll.clone(), hidden).await;
+
+    let reply = match result {
+        Ok(Ok(val)) if hidden => {
+            deliver_secret_roll(ctx, user, interaction.guild_id, channel, &to_roll, &val).await
+        }
+        Ok(Ok(val)) => val,
+        Ok(Err(why)) => why.to_string(),
+        Err(why) => {
+            eprintln!("Spawn Blocking panicked\n{:?}", why);
+            String::from("An error occurred while rolling, too many dice maybe?")
+        }
+    };
+
+    if hidden {
+        if let Err(why) = ctx.http.edit_original_interaction_response(&interaction.token, &json!({ "content": reply })).await {
+            eprintln!("Error answering a hidden roll, {:?}", why);
+        }
+        return serde_json::Value::Null;
+    }
+
+    json!({
+        "type": 4,
+        "data": {
+            "content": reply
+        }
+    })
+}
+
+/// Slash command responses can't be split over several messages, so cut the list short if needed
+const MAX_SLASH_LIST_LEN: usize = 1900;
+
+async fn chip_command(ctx: &Context, interaction: &Interaction, data: &ApplicationCommandInteractionData) -> serde_json::Value {
+    let subcommand = match data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            return json!({
                 "type": 4,
                 "data": {
-                    "content": "An error occurred while rolling, too many dice maybe?"
+                    "content": "No chip search was provided, inform Major"
                 }
-            })
+            });
         }
-        Err(why) => {
-            eprintln!("Spawn Blocking panicked\n{:?}", why);
-            json!({
+    };
+
+    let filter = get_option(&subcommand.options, subcommand.name.as_str())
+        .and_then(|o| o.as_str())
+        .unwrap_or_default();
+    let element = get_option(&subcommand.options, "element")
+        .and_then(|o| o.as_str())
+        .and_then(|e| e.parse::<Elements>().ok());
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<ChipLibrary>().expect("chip library not found");
+    let library = library_lock.read().await;
+
+    if subcommand.name == "get" {
+        let to_send = match library.search_lib_obj(filter) {
+            Ok(chip) => {
+                let user = interaction.member.as_ref().map(|m| &m.user).or(interaction.user.as_ref());
+                let renderer = Renderer::new(&data, user.map(|u| u.id).unwrap_or_default(), interaction.guild_id).await;
+                renderer.interaction_data(chip).await
+            }
+            Err(chips) => json!({
+                "content": format!("Did you mean: {}", chips.iter().map(|c| c.get_name()).collect::<Vec<&str>>().join(", "))
+            }),
+        };
+        return json!({
+            "type": 4,
+            "data": to_send
+        });
+    }
+
+    let chips = match subcommand.name.as_str() {
+        "class" => filter.parse().ok().and_then(|class| library.search_class(class, element)),
+        "type" => filter.parse().ok().and_then(|kind| library.search_type(kind, element)),
+        "range" => filter.parse().ok().and_then(|range| library.search_range(range, element)),
+        _ => None,
+    };
+
+    let to_send = match chips {
+        Some(chips) => {
+            let mut to_send = String::new();
+            for (index, chip) in chips.iter().enumerate() {
+                if to_send.len() + chip.get_name().len() > MAX_SLASH_LIST_LEN {
+                    to_send.push_str(&format!(", and {} more", chips.len() - index));
+                    break;
+                }
+                if index > 0 {
+                    to_send.push_str(", ");
+                }
+                to_send.push_str(chip.get_name());
+            }
+            to_send
+        }
+        None => String::from("nothing matched your search"),
+    };
+
+    json!({
+        "type": 4,
+        "data": {
+            "content": to_send
+        }
+    })
+}
+
+async fn ncp_command(ctx: &Context, data: &ApplicationCommandInteractionData) -> serde_json::Value {
+    let subcommand = match data.options.first() {
+        Some(subcommand) => subcommand,
+        None => {
+            return json!({
                 "type": 4,
                 "data": {
-                    "content": "An error occurred while rolling, too many dice maybe?"
+                    "content": "No NCP search was provided, inform Major"
                 }
-            })
+            });
         }
+    };
+
+    let to_search = get_option(&subcommand.options, subcommand.name.as_str())
+        .and_then(|o| o.as_str())
+        .unwrap_or_default();
+    let color = get_option(&subcommand.options, "color").and_then(|o| o.as_str());
+
+    let data = ctx.data.read().await;
+    let library_lock = data.get::<NCPLibrary>().expect("NCP library not found");
+    let library = library_lock.read().await;
+
+    let found = match subcommand.name.as_str() {
+        "cost" => match to_search.parse::<CostRange>() {
+            Ok(range) => library
+                .search_cost(range, color)
+                .unwrap_or_default()
+                .iter()
+                .map(|ncp| ncp.short_name())
+                .collect::<Vec<String>>(),
+            Err(why) => vec![why.to_string()],
+        },
+        "search" => library
+            .search_text(to_search)
+            .iter()
+            .map(|(ncp, highlighted)| format!("{}: {}", ncp.short_name(), highlighted))
+            .collect::<Vec<String>>(),
+        _ => vec![],
+    };
+
+    let mut to_send = String::new();
+    for (index, line) in found.iter().enumerate() {
+        if to_send.len() + line.len() > MAX_SLASH_LIST_LEN {
+            to_send.push_str(&format!("\nand {} more", found.len() - index));
+            break;
+        }
+        if index > 0 {
+            to_send.push('\n');
+        }
+        to_send.push_str(line);
+    }
+    if to_send.is_empty() {
+        to_send.push_str("nothing matched your search");
     }
 
+    json!({
+        "type": 4,
+        "data": {
+            "content": to_send
+        }
+    })
 }
 
 async fn shuffle_command(data: &ApplicationCommandInteractionData) -> serde_json::Value {
@@ -35,6 +35,12 @@ macro_rules! say {
 }
 
 macro_rules! reply {
+    ($ctx: ident, $msg: ident, $renderer: ident => $obj: expr) => {
+        if let Err(why) = $renderer.reply(&$ctx, &$msg, $obj).await {
+            println!("Could not send reply: {:?}", why);
+        }
+    };
+
     ($ctx: ident, $msg: ident, $say: expr) => {
         reply!($ctx, $msg, $say, false)
     };
@@ -55,12 +61,39 @@ macro_rules! long_say {
     };
 }
 
+/// Splits a trailing argument off the end of a name, if the last word parses
+pub(crate) fn split_last<T: std::str::FromStr>(args: &str) -> (&str, Option<T>) {
+    let args = args.trim();
+    match args.rsplit_once(' ') {
+        Some((name, last)) => match last.parse::<T>() {
+            Ok(val) => (name.trim(), Some(val)),
+            Err(_) => (args, None),
+        },
+        None => (args, None),
+    }
+}
+
 pub(crate) async fn send_long_message<T, S>(
     ctx: &Context,
     msg: &Message,
     to_send: T,
     separator: S,
 ) -> serenity::Result<Message>
+where
+    T: std::iter::IntoIterator,
+    T::Item: std::fmt::Display,
+    S: Into<String>,
+{
+    send_long_message_to(ctx, msg.channel_id, to_send, separator).await
+}
+
+/// Like `send_long_message`, but to any channel rather than in reply to a message
+pub(crate) async fn send_long_message_to<T, S>(
+    ctx: &Context,
+    channel_id: ChannelId,
+    to_send: T,
+    separator: S,
+) -> serenity::Result<Message>
 where
     T: std::iter::IntoIterator,
     T::Item: std::fmt::Display,
@@ -72,7 +105,7 @@ where
         let to_push = format!("{}", val);
         // a single message cannot be greater than 2000 chars
         if reply.len() + to_push.len() > 1950 {
-            msg.channel_id.say(&ctx.http, &reply).await?;
+            channel_id.say(&ctx.http, &reply).await?;
             reply.clear();
         }
         reply.push_str(&to_push);
@@ -82,7 +115,7 @@ where
     for _ in 0..sep.len() {
         reply.pop();
     }
-    msg.channel_id.say(&ctx.http, &reply).await
+    channel_id.say(&ctx.http, &reply).await
 }
 
 pub(crate) async fn send_reply<T>(
@@ -430,13 +463,23 @@ where
 
     let config = data.get::<BotData>().expect("no bot data, panicking");
 
-    let owner_id = UserId::from(config.owner);
+    dm_user(ctx, UserId::from(config.owner), to_send).await
+}
 
-    if let Some(owner) = ctx.cache.user(&owner_id).await {
-        let _ = owner.dm(ctx, |m| m.content(format!("{}", to_send))).await?;
+/// DMs the given user, fetching them over http if they aren't cached
+pub(crate) async fn dm_user<T>(
+    ctx: &Context,
+    user_id: UserId,
+    to_send: T,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    T: std::fmt::Display,
+{
+    if let Some(user) = ctx.cache.user(&user_id).await {
+        let _ = user.dm(ctx, |m| m.content(format!("{}", to_send))).await?;
     } else {
-        let owner = ctx.http.get_user(config.owner).await?;
-        let _ = owner.dm(ctx, |m| m.content(format!("{}", to_send))).await?;
+        let user = ctx.http.get_user(user_id.0).await?;
+        let _ = user.dm(ctx, |m| m.content(format!("{}", to_send))).await?;
     }
     Ok(())
 }
@@ -450,4 +493,18 @@ async fn shut_up(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
     msg.react(ctx, '\u{1f44d}').await?;
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_a_trailing_number_off_a_spaced_name() {
+        assert_eq!(split_last::<u16>("Air Shot 3"), ("Air Shot", Some(3)));
+        assert_eq!(split_last::<u16>("Air Shot"), ("Air Shot", None));
+        assert_eq!(split_last::<u16>("  Cannon  "), ("Cannon", None));
+        assert_eq!(split_last::<i64>("Paralyzer -1"), ("Paralyzer", Some(-1)));
+        assert_eq!(split_last::<u16>("3"), ("3", None));
+    }
+}